@@ -1,7 +1,13 @@
+use charon_core::evaluator::{Backend, Evaluate};
+use charon_core::utils::convert;
+use charon_core::{State, Symbol, Token, Tokens};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use wcore::{evaluator::Evaluate, models::*};
 
 fn factorial(n: f64) -> f64 {
+    factorial_with(n, Backend::Tree)
+}
+
+fn factorial_with(n: f64, backend: Backend) -> f64 {
     let code = format!(
         "
 factorial <-|
@@ -13,15 +19,148 @@ factorial <-|
         n
     );
     let mut state = State::new();
-    match state.apply(&code)[0][0] {
-        Token::Value(x) => x,
-        _ => panic!("Invalid response!"),
+    match state.apply_with(&code, backend) {
+        Ok(result) => match result[0][0] {
+            Token::Value(x) => x,
+            _ => panic!("Invalid response!"),
+        },
+        Err(_) => panic!("Invalid response!"),
+    }
+}
+
+fn sum(tokens: &Tokens) -> f64 {
+    let mut state = State::new();
+    match state.eval(tokens.clone()) {
+        Ok(result) => match result[0] {
+            Token::Value(x) => x,
+            _ => panic!("Invalid response!"),
+        },
+        Err(_) => panic!("Invalid response!"),
     }
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("factorial", |b| b.iter(|| factorial(black_box(20.))));
+    c.bench_function("factorial deep", |b| b.iter(|| factorial(black_box(150.))));
+    c.bench_function("factorial bytecode", |b| {
+        b.iter(|| factorial_with(black_box(20.), Backend::Bytecode))
+    });
+    c.bench_function("factorial deep bytecode", |b| {
+        b.iter(|| factorial_with(black_box(150.), Backend::Bytecode))
+    });
+
+    // Guards should not have to pay for the unrelated containers defined
+    // alongside the one being called.
+    let mut state = State::new();
+    let others = (0..200)
+        .map(|x| format!("other_{} <- $0 {} add\n", "x".repeat(x + 1), x))
+        .collect::<String>();
+
+    if state
+        .apply(&format!(
+            "{}\nfactorial <-|\n  $0 2 < -> 1\n  $0 $0 1 sub factorial mul\n",
+            others
+        ))
+        .is_err()
+    {
+        panic!("Invalid response!")
+    }
+
+    c.bench_function("factorial among containers", |b| {
+        b.iter(|| {
+            state
+                .eval(vec![
+                    black_box(Token::Value(20.)),
+                    Token::Container(Symbol::intern("factorial")),
+                ])
+                .is_ok()
+        })
+    });
+
+    // 0 1 add 1 add ... with 2000 additions, evaluated without parsing.
+    let block = [Token::Value(0.)]
+        .into_iter()
+        .chain(
+            [Token::Value(1.), Token::Function(Symbol::intern("add"))]
+                .into_iter()
+                .cycle()
+                .take(4000),
+        )
+        .collect::<Tokens>();
+
+    c.bench_function("long block", |b| b.iter(|| sum(black_box(&block))));
+}
+
+fn map_benchmark(c: &mut Criterion) {
+    let mut state = State::new();
+    let elements = vec!["12"; 64].join(" ");
+
+    if state
+        .apply("fib <-|\n  $0 2 < -> $0\n  $0 1 - fib $0 2 - fib +\n")
+        .is_err()
+    {
+        panic!("Invalid response!")
+    }
+
+    for function in ["map", "par_map"] {
+        let code = format!("[{}] iter `fib` {} collect_list", elements, function);
+
+        c.bench_function(function, |b| {
+            b.iter(|| state.clone().apply(black_box(&code)).is_ok())
+        });
+    }
+}
+
+fn symbol_benchmark(c: &mut Criterion) {
+    let symbols = (0..256)
+        .map(|x| Symbol::intern(&format!("symbol_{}", x)))
+        .collect::<Vec<_>>();
+    let length = |symbols: &[Symbol]| {
+        symbols
+            .iter()
+            .map(|x| black_box(*x).as_str().len())
+            .sum::<usize>()
+    };
+
+    c.bench_function("symbol as_str", |b| b.iter(|| length(&symbols)));
+
+    // Names are looked up from every thread evaluating in parallel.
+    c.bench_function("symbol as_str threads", |b| {
+        b.iter(|| {
+            std::thread::scope(|scope| {
+                (0..4)
+                    .map(|_| scope.spawn(|| (0..64).map(|_| length(&symbols)).sum::<usize>()))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|x| x.join().unwrap())
+                    .sum::<usize>()
+            })
+        })
+    });
+}
+
+fn string_benchmark(c: &mut Criterion) {
+    let text = "the quick brown fox jumps over the lazy dog ".repeat(32);
+    let string = Token::Str(text.clone());
+    let group = Token::Group(text.chars().map(Token::Char).collect());
+
+    c.bench_function("convert string", |b| b.iter(|| convert(black_box(&string))));
+    c.bench_function("convert char group", |b| {
+        b.iter(|| convert(black_box(&group)))
+    });
+    c.bench_function("eq string", |b| {
+        b.iter(|| black_box(&string) == black_box(&string.clone()))
+    });
+    c.bench_function("eq char group", |b| {
+        b.iter(|| black_box(&group) == black_box(&group.clone()))
+    });
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    map_benchmark,
+    symbol_benchmark,
+    string_benchmark
+);
 criterion_main!(benches);
@@ -0,0 +1,584 @@
+use crate::lexer::{macros, LexerToken};
+use crate::parser::{exhaustiveness, Parser};
+use crate::stdlib::{COMPLEX_TYPES, FUNCTIONS};
+use crate::utils::{highest_rec, type_of};
+use crate::{CodeBlock, Effect, State, Token, Tokens};
+use charon_ariadne::{Color, Label, Report, ReportKind, Source};
+use logos::Logos;
+use std::collections::HashMap;
+
+type Effects = HashMap<String, Effect>;
+type Span = std::ops::Range<usize>;
+
+// Code is checked a few times over so that containers used before they are
+// defined, or by each other, can be given the effects found on earlier runs.
+const PASSES: usize = 3;
+
+struct Checker<'a> {
+    effects: Effects,
+    types: &'a [String],
+    reports: Vec<Report>,
+    // The code as it was lexed, which spans index into, and as it was
+    // written, which reports show.
+    source: &'a str,
+    reference: &'a str,
+}
+
+/// Where the parts of a block were written: the predicate and consequent of
+/// each case, the default case and the stack effect annotation.
+struct Regions {
+    cases: Vec<(Span, Span)>,
+    default: Span,
+    effect: Span,
+}
+
+fn regions(source: &str, block: &CodeBlock) -> Regions {
+    let whole = block.span.clone();
+    let count = block.cases.as_ref().map_or(0, Vec::len);
+    let mut cases = vec![];
+    let mut default = None;
+    let mut inline: Option<Span> = None;
+    let mut effect = whole.clone();
+
+    // Code lifted out of a container has no text of its own.
+    if !block.container.as_ref().is_some_and(|x| x.contains('#')) {
+        for (token, span) in LexerToken::lexer(&source[whole.clone()]).spanned() {
+            let span = whole.start + span.start..whole.start + span.end;
+            let slice = &source[span.clone()];
+            let start = span.start + slice.len() - slice.trim_start().len();
+
+            match token {
+                LexerToken::GuardOption(_) => {
+                    let arrow = span.start + slice.find(" -> ").unwrap_or(0);
+
+                    cases.push((start..arrow, arrow + 4..span.end))
+                }
+                LexerToken::GuardDefault(_) => default = Some(start..span.end),
+                LexerToken::StackEffect(_) => effect = span,
+                LexerToken::BooleanGuard(_)
+                | LexerToken::Assignment(_)
+                | LexerToken::Signature(_) => inline = None,
+                LexerToken::Seperator | LexerToken::Newline => {}
+                _ => inline = Some(inline.map_or(span.clone(), |x| x.start..span.end)),
+            }
+        }
+    }
+
+    if cases.len() != count {
+        cases = vec![(whole.clone(), whole.clone()); count];
+    }
+
+    Regions {
+        cases,
+        default: default.or(inline).unwrap_or(whole),
+        effect,
+    }
+}
+
+/// The span of each token written in a region of the source, taking a
+/// collection as one token the way bundling does.
+fn positions(source: &str, region: &Span) -> Vec<Span> {
+    let mut spans: Vec<Span> = vec![];
+    let mut depth = 0;
+
+    for (token, span) in LexerToken::lexer(&source[region.clone()]).spanned() {
+        let span = region.start + span.start..region.start + span.end;
+
+        match (&token, spans.last_mut()) {
+            (LexerToken::Seperator | LexerToken::Newline, _) => continue,
+            (_, Some(last)) if depth > 0 => last.end = span.end,
+            _ => spans.push(span),
+        }
+
+        if let LexerToken::Token(Token::Special(x)) = token {
+            match x.as_str() {
+                "}" | "]" | ")" => depth -= 1,
+                _ => depth += 1,
+            }
+        }
+    }
+
+    spans
+}
+
+fn report(kind: ReportKind, title: &str, labels: Vec<(Span, String)>, reference: &str) -> Report {
+    labels
+        .into_iter()
+        .fold(
+            Report::build(kind).with_message(title),
+            |report, (span, message)| {
+                report.with_label(
+                    Label::new(span)
+                        .with_message(message)
+                        .with_color(match kind {
+                            ReportKind::Error => Color::Red,
+                            _ => Color::Yellow,
+                        }),
+                )
+            },
+        )
+        .with_source(Source::from(reference))
+        .finish()
+}
+
+impl Checker<'_> {
+    /// Whether a value only known to be of the type `actual` could be
+    /// accepted where `expected` is, so that only certain mistakes are
+    /// reported.
+    fn satisfies(&self, actual: &str, expected: &str) -> bool {
+        let accepts = |actual: &str| {
+            actual == expected
+                || (expected == "Record" && self.types.iter().any(|x| x == actual))
+                || COMPLEX_TYPES
+                    .get(expected)
+                    .is_some_and(|group| group.contains(&actual))
+        };
+
+        actual == "Any"
+            || expected == "Any"
+            || accepts(actual)
+            || (actual == "Group" && accepts("String"))
+            || (actual == "Record"
+                && (expected == "Record" || self.types.iter().any(|x| x == expected)))
+            || COMPLEX_TYPES
+                .get(actual)
+                .is_some_and(|group| group.iter().any(|x| accepts(x)))
+    }
+
+    fn effect_of(&self, token: &Token) -> Option<Effect> {
+        match token {
+            Token::Function(ident) => {
+                let (_, inputs, outputs) = FUNCTIONS[ident.as_str()];
+
+                (outputs != ["*"]).then(|| Effect {
+                    inputs: inputs.iter().rev().map(|x| x.to_string()).collect(),
+                    outputs: outputs.iter().map(|x| x.to_string()).collect(),
+                })
+            }
+            Token::Container(ident) => self.effects.get(ident.as_str()).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Runs through a sequence keeping track of only the types on the stack,
+    /// giving back what it consumed from below and what it left, or nothing
+    /// once it calls code whose effect is not known. The sequence may only
+    /// consume values from below when it is `open`, and parameters of any
+    /// type are narrowed to the first type they are used as. Reports point at
+    /// the tokens in the `region` of source the sequence was read from, or
+    /// at the whole region once the sequence no longer lines up with it.
+    fn run(
+        &mut self,
+        tokens: &Tokens,
+        region: &Span,
+        parameters: &mut Vec<String>,
+        open: bool,
+    ) -> Option<(Vec<String>, Vec<String>)> {
+        let spans = Some(positions(self.source, region))
+            .filter(|spans| spans.len() == tokens.len())
+            .unwrap_or_else(|| vec![region.clone(); tokens.len()]);
+        let mut stack: Vec<(String, usize)> = vec![];
+        let mut consumed = vec![];
+
+        for (index, token) in tokens.iter().enumerate() {
+            let effect = match token {
+                Token::Function(_) | Token::Container(_) => self.effect_of(token)?,
+                Token::ActiveLambda(_) => return None,
+                Token::Void => continue,
+                Token::Parameter(x) | Token::Capture(x) => {
+                    let parameter = parameters.get(*x).map_or("Any", String::as_str);
+
+                    stack.push((parameter.to_string(), index));
+                    continue;
+                }
+                Token::Group(contents) => {
+                    let span = &spans[index];
+                    let inner = match self.source[span.clone()].ends_with('}') {
+                        true if span != region => span.start + 1..span.end - 1,
+                        _ => region.clone(),
+                    };
+
+                    self.run(contents, &inner, parameters, false);
+                    stack.push((type_of(token), index));
+                    continue;
+                }
+                _ => {
+                    stack.push((type_of(token), index));
+                    continue;
+                }
+            };
+
+            let mut mismatches = vec![];
+
+            for (popped, expected) in effect.inputs.iter().rev().enumerate() {
+                match stack.pop() {
+                    Some((actual, origin)) if !self.satisfies(&actual, expected) => mismatches
+                        .push((
+                            spans[origin].clone(),
+                            format!("This has the type of {} but expected {}.", actual, expected),
+                        )),
+                    Some((actual, origin)) => {
+                        if let (Token::Parameter(x) | Token::Capture(x), "Any") =
+                            (&tokens[origin], actual.as_str())
+                        {
+                            if let Some(parameter) = parameters.get_mut(*x) {
+                                *parameter = expected.clone();
+                            }
+                        }
+                    }
+                    None if open => consumed.push(expected.clone()),
+                    None => {
+                        self.reports.push(report(
+                            ReportKind::Error,
+                            "Stack Underflow",
+                            vec![(
+                                spans[index].clone(),
+                                format!(
+                                    "This expects {} value(s) but only {} will be on the stack.",
+                                    effect.inputs.len(),
+                                    popped
+                                ),
+                            )],
+                            self.reference,
+                        ));
+
+                        return None;
+                    }
+                }
+            }
+
+            if !mismatches.is_empty() {
+                self.reports.push(report(
+                    ReportKind::Error,
+                    "Mismatched Types",
+                    mismatches,
+                    self.reference,
+                ))
+            }
+
+            stack.extend(effect.outputs.into_iter().map(|x| (x, index)));
+        }
+
+        consumed.reverse();
+
+        Some((consumed, stack.into_iter().map(|(x, _)| x).collect()))
+    }
+
+    fn container(&mut self, block: &CodeBlock) {
+        let name = block.container.clone().unwrap_or_default();
+        let mut cases = block.cases.clone().unwrap_or_default();
+        cases.push((vec![Token::Value(1.0)], block.default_case.clone()));
+
+        let mut parameters = match &block.signature {
+            Some(signature) => signature.parameters.clone(),
+            None => vec![
+                "Any".to_string();
+                cases
+                    .iter()
+                    .map(|(x, y)| highest_rec(x).max(highest_rec(y)))
+                    .max()
+                    .unwrap_or(0)
+            ],
+        };
+
+        let mut found: Option<Effect> = None;
+        let mut regions = regions(self.source, block);
+        regions
+            .cases
+            .push((regions.default.clone(), regions.default.clone()));
+
+        for ((predicate, consequent), (condition, span)) in cases.iter().zip(&regions.cases) {
+            self.run(predicate, condition, &mut parameters, false);
+
+            // A container without a default case is left with an empty one,
+            // which the warning about non-exhaustive patterns already covers.
+            if consequent.is_empty() {
+                continue;
+            }
+
+            // A container with a signature runs each consequent on its own and
+            // checks what it gives back, so nothing is taken from below it.
+            if let Some(signature) = &block.signature {
+                if let Some((_, stack)) = self.run(consequent, span, &mut parameters.clone(), false)
+                {
+                    if stack.len() != signature.results.len() {
+                        self.reports.push(report(
+                            ReportKind::Error,
+                            "Mismatched Results",
+                            vec![(
+                                span.clone(),
+                                format!(
+                                    "{} declares {} result(s) but this gives {}.",
+                                    name,
+                                    signature.results.len(),
+                                    stack.len()
+                                ),
+                            )],
+                            self.reference,
+                        ))
+                    } else if let Some((actual, expected)) = stack
+                        .iter()
+                        .zip(&signature.results)
+                        .find(|(actual, expected)| !self.satisfies(actual, expected))
+                    {
+                        self.reports.push(report(
+                            ReportKind::Error,
+                            "Mismatched Types",
+                            vec![(
+                                span.clone(),
+                                format!(
+                                    "{} declares a result of {} but this gives {}.",
+                                    name, expected, actual
+                                ),
+                            )],
+                            self.reference,
+                        ))
+                    }
+                }
+
+                continue;
+            }
+
+            let (consumed, outputs) = match self.run(consequent, span, &mut parameters, true) {
+                Some(effect) => effect,
+                None => continue,
+            };
+
+            let effect = Effect {
+                inputs: [consumed, parameters.clone()].concat(),
+                outputs,
+            };
+
+            found = match found {
+                None => Some(effect),
+                Some(earlier)
+                    if earlier.inputs.len() != effect.inputs.len()
+                        || earlier.outputs.len() != effect.outputs.len() =>
+                {
+                    self.reports.push(report(
+                        ReportKind::Warning,
+                        "Inconsistent Stack Effect",
+                        vec![(
+                            span.clone(),
+                            format!(
+                                "This case of {} takes {} value(s) and gives {}, unlike an earlier case which takes {} and gives {}.",
+                                name,
+                                effect.inputs.len(),
+                                effect.outputs.len(),
+                                earlier.inputs.len(),
+                                earlier.outputs.len()
+                            ),
+                        )],
+                        self.reference,
+                    ));
+
+                    Some(earlier)
+                }
+                Some(earlier) => {
+                    let merge = |x: &[String], y: &[String]| {
+                        x.iter()
+                            .zip(y)
+                            .map(|(x, y)| if x == y { x.clone() } else { "Any".to_string() })
+                            .collect()
+                    };
+
+                    Some(Effect {
+                        inputs: merge(&earlier.inputs, &effect.inputs),
+                        outputs: merge(&earlier.outputs, &effect.outputs),
+                    })
+                }
+            };
+
+            // Later cases calling the container recursively use what the
+            // earlier ones have shown its effect to be.
+            if let Some(effect) = &found {
+                self.effects.insert(name.clone(), effect.clone());
+            }
+        }
+
+        let annotation = match &block.effect {
+            Some(annotation) => annotation,
+            None => return,
+        };
+
+        let computed = match &block.signature {
+            Some(signature) => Effect {
+                inputs: signature.parameters.clone(),
+                outputs: signature.results.clone(),
+            },
+            None => match found {
+                Some(effect) => effect,
+                None => {
+                    // When its effect can not be worked out the annotation is
+                    // trusted, so that code using the container is still checked.
+                    self.effects.insert(name, declared(annotation));
+                    return;
+                }
+            },
+        };
+
+        let agrees = |declared: &[String], computed: &[String]| {
+            declared.len() == computed.len()
+                && declared
+                    .iter()
+                    .zip(computed)
+                    .all(|(x, y)| !is_type_name(x) || self.satisfies(y, x))
+        };
+
+        if !agrees(&annotation.inputs, &computed.inputs)
+            || !agrees(&annotation.outputs, &computed.outputs)
+        {
+            self.reports.push(report(
+                ReportKind::Error,
+                "Mismatched Stack Effect",
+                vec![(
+                    regions.effect,
+                    format!(
+                        "The body of {} has the effect {}.",
+                        name,
+                        notation(&computed)
+                    ),
+                )],
+                self.reference,
+            ))
+        }
+    }
+}
+
+fn is_type_name(label: &str) -> bool {
+    label.starts_with(|x: char| x.is_ascii_uppercase())
+}
+
+/// The effect an annotation promises, where any label naming a type stands
+/// for that type and the rest could be anything.
+fn declared(annotation: &Effect) -> Effect {
+    let types = |labels: &[String]| {
+        labels
+            .iter()
+            .map(|x| {
+                if is_type_name(x) {
+                    x.clone()
+                } else {
+                    "Any".to_string()
+                }
+            })
+            .collect()
+    };
+
+    Effect {
+        inputs: types(&annotation.inputs),
+        outputs: types(&annotation.outputs),
+    }
+}
+
+fn notation(effect: &Effect) -> String {
+    [
+        vec!["("],
+        effect.inputs.iter().map(String::as_str).collect(),
+        vec!["--"],
+        effect.outputs.iter().map(String::as_str).collect(),
+        vec![")"],
+    ]
+    .concat()
+    .join(" ")
+}
+
+fn signatures(blocks: &[CodeBlock]) -> Effects {
+    blocks
+        .iter()
+        .filter_map(|block| {
+            let effect = match (&block.signature, &block.effect) {
+                (Some(signature), _) => Effect {
+                    inputs: signature.parameters.clone(),
+                    outputs: signature.results.clone(),
+                },
+                (None, Some(annotation)) => declared(annotation),
+                (None, None) => return None,
+            };
+
+            Some((block.container.clone()?, effect))
+        })
+        .collect()
+}
+
+/// Parses code the way it would be run, giving back the code as it was
+/// lexed alongside the blocks and any warnings about them.
+fn parse(code: &str) -> (String, Result<Vec<CodeBlock>, Vec<Report>>, Vec<Report>) {
+    let cleaned_code = macros(code.to_string());
+    let lex = LexerToken::lexer(&cleaned_code)
+        .spanned()
+        .collect::<Vec<_>>();
+    let state = State::new();
+    let warnings = exhaustiveness(&lex, code, &state);
+    let blocks = state.parser(lex, code);
+
+    (cleaned_code, blocks, warnings)
+}
+
+fn analyse(blocks: &[CodeBlock], source: &str, reference: &str) -> (Effects, Vec<Report>) {
+    let types = blocks
+        .iter()
+        .flat_map(|block| &block.default_case)
+        .filter_map(|token| match token {
+            Token::Record(name, _) | Token::Variant(name, _, _) => Some(name.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut effects = signatures(blocks);
+
+    for pass in 1..=PASSES {
+        let mut checker = Checker {
+            effects: effects.clone(),
+            types: &types,
+            reports: vec![],
+            source,
+            reference,
+        };
+
+        for block in blocks {
+            match block.container {
+                Some(_) => checker.container(block),
+                None => {
+                    let region = regions(source, block).default;
+
+                    checker.run(&block.default_case, &region, &mut vec![], false);
+                }
+            }
+        }
+
+        if pass == PASSES || checker.effects == effects {
+            return (checker.effects, checker.reports);
+        }
+
+        effects = checker.effects;
+    }
+
+    (effects, vec![])
+}
+
+/// Checks code without running it, reporting stack underflows, type
+/// mismatches and stack effects that disagree with their annotations, along
+/// with anything the parser would report.
+pub fn check(code: &str) -> Vec<Report> {
+    match parse(code) {
+        (source, Ok(blocks), warnings) => warnings
+            .into_iter()
+            .chain(analyse(&blocks, &source, code).1)
+            .collect(),
+        (_, Err(errors), warnings) => errors.into_iter().chain(warnings).collect(),
+    }
+}
+
+/// Works out the stack effect of each container defined in the code, for
+/// tools which show or check them. Containers that run code whose effect
+/// can not be known before it runs are left out.
+pub fn effects(code: &str) -> Result<HashMap<String, Effect>, Vec<Report>> {
+    let (source, blocks, _) = parse(code);
+
+    Ok(analyse(&blocks?, &source, code)
+        .0
+        .into_iter()
+        .filter(|(name, _)| !name.contains('#'))
+        .collect())
+}
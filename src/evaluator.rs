@@ -1,28 +1,133 @@
 use crate::lexer::{macros, LexerToken};
-use crate::parser::Parser;
-use crate::stdlib::FUNCTIONS;
-use crate::utils::{Function, Utils};
-use crate::{CodeBlock, State, Token, Tokens};
-use std::collections::VecDeque;
+use crate::parser::{exhaustiveness, Parser};
+use crate::utils::{has_type, type_of, Function, Utils};
+use crate::vm::codeblock_run;
+use crate::{CodeBlock, Container, FunctionRef, Signature, State, Symbol, Token, Tokens};
+use std::sync::Arc;
 
-use charon_ariadne::Report;
+use charon_ariadne::{Color, Label, Report, ReportKind, Source};
 use logos::Logos;
 
+/// Checks the values a container gave back against the results declared in
+/// its signature, labelling the call at `position` in the reference code,
+/// which is only built when there is something to report.
+#[allow(clippy::result_large_err)]
+pub(crate) fn check_results(
+    ident: &str,
+    signature: &Signature,
+    results: &[Token],
+    reference_code: impl FnOnce() -> Tokens,
+    position: usize,
+) -> Result<(), Report> {
+    let (title, message) = if results.len() != signature.results.len() {
+        (
+            "Mismatched Results",
+            format!(
+                "{} declares {} result(s) but gave {}.",
+                ident,
+                signature.results.len(),
+                results.len()
+            ),
+        )
+    } else if let Some((token, expected)) = results
+        .iter()
+        .zip(&signature.results)
+        .find(|(token, expected)| !has_type(token, expected))
+    {
+        (
+            "Mismatched Types",
+            format!(
+                "{} declares a result of {} but gave {}.",
+                ident,
+                expected,
+                type_of(token)
+            ),
+        )
+    } else {
+        return Ok(());
+    };
+
+    let literal = reference_code().literal_enumerate();
+
+    Err(Report::build(ReportKind::Error)
+        .with_message(title)
+        .with_label(
+            Label::new(literal.1[position].clone())
+                .with_message(message)
+                .with_color(Color::Red),
+        )
+        .with_source(Source::from(literal.0))
+        .finish())
+}
+
+/// A call to a container with a signature whose consequent is still being
+/// run. The consequent is spliced onto the execution stack above `depth`, so
+/// the call is over once the stack is back down to it, at which point the
+/// values above `base` are what it gave back.
+struct Call {
+    ident: Symbol,
+    signature: Signature,
+    depth: usize,
+    base: usize,
+    tail_calls: Vec<(Symbol, Signature)>,
+}
+
+/// Defines a container in the state from its parsed code block, with its
+/// default case last as one that always applies.
+pub(crate) fn define(state: &mut State, codeblock: CodeBlock) {
+    let container = Symbol::intern(&codeblock.container.unwrap());
+    let mut cases = codeblock.cases.unwrap_or_default();
+
+    cases.push((vec![Token::Value(1.0)], codeblock.default_case));
+
+    state.insert(
+        container,
+        Container {
+            cases: Arc::new(cases),
+            signature: codeblock.signature,
+        },
+    );
+}
+
+/// The backend that runs code given to [`Evaluate::apply_with`]. Both give
+/// the same results, but the bytecode backend compiles containers first so
+/// that calls do not look them up by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Tree,
+    Bytecode,
+}
+
+#[allow(clippy::result_large_err)]
 pub trait Evaluate {
     fn apply(&mut self, code: &str) -> Result<Vec<Tokens>, Vec<Report>>;
+    fn apply_with(&mut self, code: &str, backend: Backend) -> Result<Vec<Tokens>, Vec<Report>>;
+    fn warnings(&self, code: &str) -> Vec<Report>;
     fn codeblock_eval(&mut self, data: Vec<CodeBlock>) -> Result<Vec<Tokens>, Report>;
     fn eval(&mut self, data: Tokens) -> Result<Tokens, Report>;
 }
 
 impl Evaluate for State {
     fn apply(&mut self, code: &str) -> Result<Vec<Tokens>, Vec<Report>> {
+        self.apply_with(code, Backend::Tree)
+    }
+
+    fn apply_with(&mut self, code: &str, backend: Backend) -> Result<Vec<Tokens>, Vec<Report>> {
         let cleaned_code = macros(code.to_string());
-        let lex = LexerToken::lexer(&cleaned_code);
+        let lex = LexerToken::lexer(&cleaned_code)
+            .spanned()
+            .collect::<Vec<_>>();
 
-        let parse = self.parser(lex.spanned().collect::<Vec<_>>(), code);
+        let parse = self.parser(lex, code);
 
         if let Ok(parsed) = parse {
-            Ok(match self.codeblock_eval(parsed) {
+            let result = match backend {
+                Backend::Tree => self.codeblock_eval(parsed),
+                Backend::Bytecode => codeblock_run(self, parsed),
+            };
+
+            Ok(match result {
                 Ok(x) => x,
                 Err(reports) => return Err(vec![reports]),
             })
@@ -31,22 +136,24 @@ impl Evaluate for State {
         }
     }
 
+    /// Warns about code that would run but may not do what was meant, such as
+    /// containers whose patterns leave out a variant of a union, given the
+    /// unions defined in the state so far. Nothing is run or defined.
+    fn warnings(&self, code: &str) -> Vec<Report> {
+        let cleaned_code = macros(code.to_string());
+        let lex = LexerToken::lexer(&cleaned_code)
+            .spanned()
+            .collect::<Vec<_>>();
+
+        exhaustiveness(&lex, code, self)
+    }
+
     fn codeblock_eval(&mut self, data: Vec<CodeBlock>) -> Result<Vec<Tokens>, Report> {
         let mut result: Vec<Tokens> = Vec::new();
 
         for codeblock in data {
             match codeblock.container {
-                Some(container) => {
-                    let mut cases = vec![];
-
-                    if let Some(container_cases) = codeblock.cases {
-                        cases.append(&mut container_cases.clone())
-                    }
-
-                    cases.push((vec![Token::Value(1.0)], codeblock.default_case));
-
-                    self.insert(container, cases);
-                }
+                Some(_) => define(self, codeblock),
                 None => result.push(self.eval(codeblock.default_case)?),
             }
         }
@@ -55,56 +162,82 @@ impl Evaluate for State {
     }
 
     fn eval(&mut self, data: Tokens) -> Result<Tokens, Report> {
-        let mut execution_stack: VecDeque<Token> = VecDeque::from(data);
-        let mut parameter_stack: VecDeque<Token> = VecDeque::new();
+        // The tokens left to run are kept in reverse, so that the next one is
+        // popped off the end and a consequent is spliced in front of the rest
+        // without moving them.
+        let mut execution_stack: Tokens = data;
+        let mut parameter_stack: Tokens = vec![];
 
-        while let Some(token) = execution_stack.pop_front() {
-            match token {
-                Token::Function(ref ident) => {
-                    let parameters = Vec::from(parameter_stack.clone()).get_par(
-                        token.clone(),
-                        [
-                            Vec::from(parameter_stack.clone()),
-                            Vec::from(execution_stack.clone()),
-                        ]
-                        .concat(),
-                        self,
-                    )?;
+        // A container with a signature called last, with nothing beneath it,
+        // gives back all that is left here, so it takes over rather than being
+        // run as a call of its own and what it gives back is checked at the
+        // end. This lets recursion through it run in constant space.
+        let mut tail_calls: Vec<(Symbol, Signature)> = vec![];
+        let mut calls: Vec<Call> = vec![];
 
-                    let mut n = parameters.clone().len();
-                    while n > 0 {
-                        parameter_stack.pop_back();
-                        n -= 1;
-                    }
+        execution_stack.reverse();
 
-                    let result = FUNCTIONS.get(ident).unwrap().0(self, parameters)?;
+        loop {
+            while let Some(call) = calls.pop_if(|x| x.depth == execution_stack.len()) {
+                let results = &parameter_stack[call.base..];
 
-                    execution_stack.push_front(result);
-                }
-                Token::Container(ref ident) => {
-                    let parameters = Vec::from(parameter_stack.clone()).get_par(
-                        token.clone(),
-                        [
-                            Vec::from(parameter_stack.clone()),
-                            Vec::from(execution_stack.clone()),
-                        ]
-                        .concat(),
-                        self,
+                for (ident, signature) in call.tail_calls.iter().rev() {
+                    check_results(
+                        ident.as_str(),
+                        signature,
+                        results,
+                        || vec![Token::Container(*ident)],
+                        0,
                     )?;
+                }
 
-                    let mut n = parameters.clone().len();
-                    while n > 0 {
-                        parameter_stack.pop_back();
-                        n -= 1;
-                    }
+                check_results(
+                    call.ident.as_str(),
+                    &call.signature,
+                    results,
+                    || {
+                        parameter_stack[..call.base]
+                            .iter()
+                            .chain([&Token::Container(call.ident)])
+                            .chain(execution_stack.iter().rev())
+                            .cloned()
+                            .collect()
+                    },
+                    call.base,
+                )?;
+            }
+
+            let token = match execution_stack.pop() {
+                Some(token) => token,
+                None => break,
+            };
+            let base = calls.last().map_or(0, |x| x.base);
 
+            match token {
+                Token::Function(ref ident) => {
+                    let parameters = parameter_stack.get_par(&token, self, base)?;
+                    match ident.builtin().unwrap().0 {
+                        FunctionRef::Single(function) => {
+                            execution_stack.push(function(self, parameters)?)
+                        }
+                        FunctionRef::Many(function) => {
+                            parameter_stack.extend(function(self, parameters)?)
+                        }
+                    }
+                }
+                Token::Container(ref ident) => {
+                    let parameters = parameter_stack.get_par(&token, self, base)?;
 
-                    let cases = self.get(ident.as_str()).unwrap();
+                    let container = self.get(ident).unwrap();
+                    let cases = Arc::clone(&container.cases);
+                    let signature = container.signature.clone();
 
                     let mut selected_consequent: Option<&Vec<Token>> = None;
 
-                    for (predictate, consequent) in cases {
-                        if self.clone().eval(self.resolve(predictate, &parameters))?
+                    // Guards only read the state, since containers can not be
+                    // defined while evaluating, so they run against it as is.
+                    for (predictate, consequent) in cases.iter() {
+                        if self.eval(self.resolve(predictate, &parameters))?
                             == vec![Token::Value(1.0)]
                         {
                             selected_consequent = Some(consequent);
@@ -112,47 +245,62 @@ impl Evaluate for State {
                         }
                     }
 
-                    execution_stack = VecDeque::from(
-                        [
-                            self.resolve(selected_consequent.unwrap(), &parameters),
-                            Vec::from(execution_stack.clone()),
-                        ]
-                        .concat(),
-                    );
-                }
-                Token::ActiveLambda(ref lambda) => {
-                     let parameters = Vec::from(parameter_stack.clone()).get_par(
-                        token.clone(),
-                        [
-                            Vec::from(parameter_stack.clone()),
-                            Vec::from(execution_stack.clone()),
-                        ]
-                        .concat(),
-                        self,
-                    )?;
+                    let consequent = self.resolve(selected_consequent.unwrap(), &parameters);
 
-                    let mut n = parameters.clone().len();
-                    while n > 0 {
-                        parameter_stack.pop_back();
-                        n -= 1;
-                    }
+                    // A container with a signature runs its consequent as a
+                    // call of its own, so that what it gives back can be
+                    // checked against it once the call is over.
+                    match signature {
+                        Some(signature)
+                            if calls.last().map_or(0, |x| x.depth) == execution_stack.len()
+                                && parameter_stack.len() == base =>
+                        {
+                            let tail_calls = match calls.last_mut() {
+                                Some(call) => &mut call.tail_calls,
+                                None => &mut tail_calls,
+                            };
+
+                            if !tail_calls.iter().any(|(x, _)| x == ident) {
+                                tail_calls.push((*ident, signature));
+                            }
+
+                            execution_stack.extend(consequent.into_iter().rev());
+                        }
+                        Some(signature) => {
+                            calls.push(Call {
+                                ident: *ident,
+                                signature,
+                                depth: execution_stack.len(),
+                                base: parameter_stack.len(),
+                                tail_calls: vec![],
+                            });
 
-                    execution_stack = VecDeque::from(
-                        [
-                            self.resolve(&lambda, &parameters),
-                            Vec::from(execution_stack.clone()),
-                        ]
-                        .concat(),
-                    );
+                            execution_stack.extend(consequent.into_iter().rev());
+                        }
+                        None => execution_stack.extend(consequent.into_iter().rev()),
+                    }
                 }
-                Token::Group(contents) => {
-                    parameter_stack.push_back(Token::Group(self.eval(contents)?))
+                Token::ActiveLambda(ref lambda) => {
+                    let parameters = parameter_stack.get_par(&token, self, base)?;
+
+                    execution_stack.extend(self.resolve(lambda, &parameters).into_iter().rev());
                 }
+                Token::Group(contents) => parameter_stack.push(Token::Group(self.eval(contents)?)),
                 Token::Void => continue,
-                _ => parameter_stack.push_back(token),
+                _ => parameter_stack.push(token),
             }
         }
 
-        Ok(Vec::from(parameter_stack))
+        for (ident, signature) in tail_calls.iter().rev() {
+            check_results(
+                ident.as_str(),
+                signature,
+                &parameter_stack,
+                || vec![Token::Container(*ident)],
+                0,
+            )?;
+        }
+
+        Ok(parameter_stack)
     }
 }
@@ -1,4 +1,4 @@
-use crate::Token;
+use crate::{Effect, Range, Signature, Token};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use logos::{Lexer, Logos};
@@ -25,12 +25,7 @@ pub fn macros(text: String) -> String {
 fn string(lex: &mut Lexer<LexerToken>) -> Token {
     let slice = lex.slice();
 
-    Token::Group(
-        slice[1..slice.len() - 1]
-            .chars()
-            .map(Token::Char)
-            .collect::<Vec<_>>(),
-    )
+    Token::Str(slice[1..slice.len() - 1].to_string())
 }
 
 fn container_literal(lex: &mut Lexer<LexerToken>) -> String {
@@ -58,11 +53,77 @@ fn assignment(lex: &mut Lexer<LexerToken>) -> String {
     slice[..slice.len() - 3].to_string()
 }
 
+fn signature(lex: &mut Lexer<LexerToken>) -> Signature {
+    let slice = lex.slice().trim_end().trim_end_matches('|');
+    let (parameters, results) = slice[1..slice.len() - 2].split_once("->").unwrap();
+    let types = |x: &str| x.split_whitespace().map(str::to_string).collect();
+
+    Signature {
+        parameters: types(parameters),
+        results: types(results),
+    }
+}
+
+fn stack_effect(lex: &mut Lexer<LexerToken>) -> Effect {
+    let slice = lex.slice();
+    let (inputs, outputs) = slice[1..slice.len() - 1].split_once(" --").unwrap();
+    let names = |x: &str| x.split_whitespace().map(str::to_string).collect();
+
+    Effect {
+        inputs: names(inputs),
+        outputs: names(outputs),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Definition {
+    Record(String, Vec<String>),
+    Union(String, Vec<(String, Vec<String>)>),
+}
+
+fn definition(lex: &mut Lexer<LexerToken>) -> Option<Definition> {
+    let (name, body) = lex.slice().split_once(" <-:").unwrap();
+    let is_name = |x: &str| x.chars().all(|x| x.is_ascii_alphabetic() || x == '_');
+
+    if !body.contains(':') {
+        let fields = body
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        return fields
+            .iter()
+            .all(|x| is_name(x))
+            .then(|| Definition::Record(name.to_string(), fields));
+    }
+
+    let mut variants = vec![];
+
+    for variant in body.split('|') {
+        let mut words = variant.split_whitespace();
+        let tag = words.next()?.strip_prefix(':')?;
+        let fields = words.map(str::to_string).collect::<Vec<_>>();
+
+        if tag.is_empty() || !is_name(tag) || !fields.iter().all(|x| is_name(x)) {
+            return None;
+        }
+
+        variants.push((tag.to_string(), fields));
+    }
+
+    Some(Definition::Union(name.to_string(), variants))
+}
+
 fn parameter(lex: &mut Lexer<LexerToken>) -> Token {
     let slice = lex.slice()[1..].parse::<usize>().unwrap();
     Token::Parameter(slice)
 }
 
+fn capture(lex: &mut Lexer<LexerToken>) -> Token {
+    let slice = lex.slice()[1..].parse::<usize>().unwrap();
+    Token::Capture(slice)
+}
+
 fn range(lex: &mut Lexer<LexerToken>) -> Token {
     let slice = &lex.slice()[1..];
 
@@ -77,9 +138,23 @@ fn range(lex: &mut Lexer<LexerToken>) -> Token {
     }
 }
 
+fn slice(lex: &mut Lexer<LexerToken>) -> Token {
+    let bounds = lex.slice()[1..]
+        .split("..")
+        .map(|x| x.parse::<usize>().ok())
+        .collect::<Vec<_>>();
+
+    Token::Range(match (bounds[0], bounds[1]) {
+        (Some(start), Some(end)) => Range::Full(start..=end),
+        (Some(start), None) => Range::To(start..),
+        (None, Some(end)) => Range::From(..end.saturating_add(1)),
+        (None, None) => Range::To(0..),
+    })
+}
+
 #[derive(Logos, Debug, Clone, PartialEq)]
 pub enum LexerToken {
-    #[regex(r"[a-zA-Z_] <-\|", boolean_guard)]
+    #[regex(r"[a-zA-Z_]+ <-\|", boolean_guard)]
     BooleanGuard(String),
 
     #[regex(r"\n  [^\n]*", |default| default.slice()[1..].trim().to_string())]
@@ -91,19 +166,31 @@ pub enum LexerToken {
     #[regex(r"[a-zA-Z_]+ <- *", assignment)]
     Assignment(String),
 
+    #[regex(r"[a-zA-Z_]+ <-:[^\n]*", definition)]
+    Definition(Definition),
+
+    #[regex(r":( [a-zA-Z_]+)* ->( [a-zA-Z_]+)* <-(\|| *)", signature)]
+    Signature(Signature),
+
+    #[regex(r"\(( [^()\s-][^()\s]*)* --( [^()\s-][^()\s]*)* \)", stack_effect)]
+    StackEffect(Effect),
+
     #[regex("\"[^\"]*\"", string)]
     #[regex(r"-?\d+(\.\d+)?", |number| Token::Value(number.slice().parse().unwrap()))]
     #[regex("'.'", |character| Token::Char(character.slice().chars().nth(1).unwrap()))]
     #[regex(r"\$\d+", parameter)]
+    #[regex(r"\^\d+", capture)]
     #[regex(r"@\d+\.\.\d+", range)]
-    #[regex(r":[a-zA-Z\+\-\*/%><\|&_]+", |atom| Token::Atom(atom.slice()[1..].to_string()))]
-    #[regex(r"\{|\}|\[|\]", |s| Token::Special(s.slice().to_string()))]
+    #[regex(r"#(\d+)?\.\.(\d+)?", slice)]
+    #[regex(r":[a-zA-Z\+\-\*/%><=!\|&_]+", |atom| Token::Atom(atom.slice()[1..].to_string()))]
+    #[regex(r"\{|\}|\[|\]|%\{|#\{|\\\(|\)", |s| Token::Special(s.slice().to_string()))]
     Token(Token),
 
-    #[regex(r"[a-zA-Z\+\-\*/%><\|&_]+", |func| func.slice().to_string())]
+    #[regex(r"[a-zA-Z\+\-\*/%><=!\|&_]+", |func| func.slice().to_string())]
+    #[token("2dup", |func| func.slice().to_string())]
     Function(String),
 
-    #[regex(r"`[a-zA-Z\+\-\*/%><\|&_]+`", container_literal)]
+    #[regex(r"`[a-zA-Z\+\-\*/%><=!\|&_]+`", container_literal)]
     FunctionLiteral(String),
 
     #[token(" ")]
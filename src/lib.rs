@@ -1,29 +1,48 @@
+pub mod checker;
 pub mod evaluator;
 mod lexer;
 mod parser;
+mod pattern;
+mod scope;
+// Every builtin gives back a Report when it fails, which is large but only
+// built on the way out of evaluation.
+#[allow(clippy::result_large_err)]
 mod stdlib;
+mod symbol;
 pub mod utils;
+mod vm;
+
+pub use symbol::Symbol;
 
 use charon_ariadne::Report;
 use std::collections::HashMap;
 use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Value(f64),
-    Function(String),
+    Function(Symbol),
     FunctionLiteral(String),
-    Container(String),
+    Container(Symbol),
     ContainerLiteral(String),
     Lambda(Vec<Token>),
     ActiveLambda(Vec<Token>),
     Parameter(usize),
+    Capture(usize),
     Atom(String),
     Char(char),
+    Str(String),
     Special(String),
     Group(Vec<Token>),
     List(Vec<Token>),
     Iterator(Vec<Token>),
+    Map(Vec<(Token, Token)>),
+    Set(Vec<Token>),
+    Record(String, Vec<(String, Token)>),
+    Variant(String, String, Vec<Token>),
+    Range(Range),
     Null,
     Void,
 }
@@ -34,11 +53,38 @@ impl fmt::Display for Token {
     }
 }
 
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Signature {
+    pub parameters: Vec<String>,
+    pub results: Vec<String>,
+}
+
+/// What a container takes from the stack and leaves in their place, both
+/// listed from the deepest value to the top. Computed effects hold types,
+/// while an annotation like `( a b -- c )` may name them instead.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Effect {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
 #[derive(PartialEq, Debug, Default, Clone)]
 pub struct CodeBlock {
     pub container: Option<String>,
     pub cases: Option<Vec<(Tokens, Tokens)>>,
     pub default_case: Tokens,
+    pub signature: Option<Signature>,
+    pub effect: Option<Effect>,
+    /// Where in the source the block was read from, for reports about it.
+    pub span: std::ops::Range<usize>,
+}
+
+/// A container as it is stored in the state. Its cases are shared so that a
+/// call can hold on to them while evaluating guards against the state.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Container {
+    pub cases: Arc<Vec<(Tokens, Tokens)>>,
+    pub signature: Option<Signature>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -49,5 +95,52 @@ pub enum Range {
 }
 
 pub type Tokens = Vec<Token>;
-pub(crate) type FunctionRef = fn(&mut State, Tokens) -> Result<Token, Report>;
-pub type State = HashMap<String, Vec<(Tokens, Tokens)>>;
+/// A builtin, which either gives back a single token that is run next or any
+/// number of values that are left on the stack as they are.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FunctionRef {
+    Single(fn(&mut State, Tokens) -> Result<Token, Report>),
+    Many(fn(&mut State, Tokens) -> Result<Tokens, Report>),
+}
+
+/// The containers defined so far, along with how code is evaluated against
+/// them. Containers sit behind an Arc, so that threads evaluating in parallel
+/// can each hold the state without copying them, which only happens when one
+/// is defined while the state is shared.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    containers: Arc<HashMap<Symbol, Container>>,
+    parallel: bool,
+}
+
+impl State {
+    pub fn new() -> State {
+        State::default()
+    }
+
+    /// Whether map spreads the evaluation of pure lambdas over large
+    /// iterators across threads, as par_map and par_filter always do.
+    pub fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Lets map evaluate across threads. It is off by default since the
+    /// threads only pay for themselves when there is enough work to share.
+    pub fn set_parallel(&mut self, enabled: bool) {
+        self.parallel = enabled
+    }
+}
+
+impl Deref for State {
+    type Target = HashMap<Symbol, Container>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.containers
+    }
+}
+
+impl DerefMut for State {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Arc::make_mut(&mut self.containers)
+    }
+}
@@ -1,10 +1,267 @@
-use crate::lexer::LexerToken as LToken;
+use crate::lexer::{Definition, LexerToken as LToken};
+use crate::pattern::{self, Cover};
+use crate::scope;
 use crate::stdlib::FUNCTIONS;
 use crate::utils::Utils;
-use crate::{CodeBlock, State, Token, Tokens};
+use crate::{CodeBlock, State, Symbol, Token, Tokens};
 use charon_ariadne::{Color, Label, Report, ReportKind, Source};
 use logos::{Logos, Span};
 use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// The unions the state knows of, found from the containers that make their
+/// variants, followed by those defined in the code, each with its tags.
+fn unions(code: &[(LToken, Span)], state: &State) -> Vec<(String, Vec<String>)> {
+    let mut known = state
+        .iter()
+        .filter_map(
+            |(name, container)| match container.cases.last()?.1.as_slice() {
+                [Token::Variant(union, tag, _)] if tag == name.as_str() => {
+                    Some((union.to_string(), tag.to_string()))
+                }
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+    let mut unions: Vec<(String, Vec<String>)> = vec![];
+
+    known.sort();
+
+    for (union, tag) in known {
+        match unions.iter_mut().find(|(x, _)| *x == union) {
+            Some((_, tags)) => tags.push(tag),
+            None => unions.push((union, vec![tag])),
+        }
+    }
+
+    for (token, _) in code {
+        if let LToken::Definition(Definition::Union(name, variants)) = token {
+            unions.push((
+                name.to_string(),
+                variants.iter().map(|(tag, _)| tag.to_string()).collect(),
+            ));
+        }
+    }
+
+    unions
+}
+
+/// How far a container's pattern cases go towards matching every value.
+enum Coverage {
+    Complete,
+    Missing(String, Vec<String>),
+    Unknown,
+}
+
+/// Whether patterns of lists or groups match every length of one of them,
+/// as `([])` and `([h ...t])` do.
+fn every_length<'a>(covers: impl Iterator<Item = &'a Cover> + Clone) -> bool {
+    covers.clone().any(|cover| match cover {
+        Cover::Sequence(kind, shortest, true) => (0..*shortest).all(|length| {
+            covers.clone().any(
+                |cover| matches!(cover, Cover::Sequence(x, y, false) if x == kind && *y == length),
+            )
+        }),
+        _ => false,
+    })
+}
+
+/// Whether the cases are sure to match whatever they are given. Only a case
+/// which matches anything for every other parameter counts towards covering
+/// the values of one. Where they match a parameter on the variants of a union
+/// and leave some out, the first such union is given with the tags left out.
+fn coverage(cases: &[Vec<Cover>], unions: &[(String, Vec<String>)]) -> Coverage {
+    let anything = |case: &[Cover], except: Option<usize>| {
+        case.iter()
+            .enumerate()
+            .all(|(index, cover)| Some(index) == except || *cover == Cover::Anything)
+    };
+
+    if cases.iter().any(|case| anything(case, None)) {
+        return Coverage::Complete;
+    }
+
+    let width = cases.iter().map(Vec::len).max().unwrap_or(0);
+    let mut found = Coverage::Unknown;
+
+    for position in 0..width {
+        let alone = cases
+            .iter()
+            .filter(|case| anything(case, Some(position)))
+            .filter_map(|case| case.get(position));
+
+        if every_length(alone.clone()) {
+            return Coverage::Complete;
+        }
+
+        let tag = cases.iter().find_map(|case| match case.get(position) {
+            Some(Cover::Variant(tag, _)) => Some(tag),
+            _ => None,
+        });
+
+        // A union defined later takes the place of one of the same tags.
+        let (union, tags) =
+            match tag.and_then(|tag| unions.iter().rev().find(|(_, tags)| tags.contains(tag))) {
+                Some(union) => union,
+                None => continue,
+            };
+
+        let missing = tags
+            .iter()
+            .filter(|tag| {
+                !alone
+                    .clone()
+                    .any(|cover| matches!(cover, Cover::Variant(x, true) if x == *tag))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return Coverage::Complete;
+        } else if let Coverage::Unknown = found {
+            found = Coverage::Missing(union.to_string(), missing);
+        }
+    }
+
+    found
+}
+
+/// Warns about containers with pattern cases but no default case to fall back
+/// on, unless the patterns are sure to match whatever they are given, naming
+/// the variants left out where they match on those of a union.
+pub(crate) fn exhaustiveness(
+    code: &[(LToken, Span)],
+    reference: &str,
+    state: &State,
+) -> Vec<Report> {
+    let unions = unions(code, state);
+    let mut warnings = vec![];
+    let mut container: Option<(String, Span)> = None;
+    let mut cases: Vec<Vec<Cover>> = vec![];
+    let mut patterns = false;
+    let mut default = false;
+
+    for (token, span) in code.iter().chain([(LToken::Newline, 0..0)].iter()) {
+        match token {
+            LToken::BooleanGuard(name) | LToken::Assignment(name) => {
+                container = Some((name.clone(), span.clone()))
+            }
+            LToken::GuardOption((x, _)) if pattern::is_pattern(x) => {
+                patterns = true;
+                cases.extend(pattern::covers(x))
+            }
+            LToken::GuardDefault(_) => default = true,
+            LToken::Newline => {
+                if let (Some((name, span)), true, false) = (&container, patterns, default) {
+                    let message = match coverage(&cases, &unions) {
+                        Coverage::Complete => None,
+                        Coverage::Missing(union, tags) => Some(format!(
+                            "{} has no case for {} of {}, nor a default case.",
+                            name,
+                            tags.iter()
+                                .map(|tag| format!(":{}", tag))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            union
+                        )),
+                        Coverage::Unknown => Some(format!(
+                            "{} has no default case for values its patterns do not match.",
+                            name
+                        )),
+                    };
+
+                    if let Some(message) = message {
+                        warnings.push(
+                            Report::build(ReportKind::Warning)
+                                .with_message("Non-exhaustive Patterns")
+                                .with_label(
+                                    Label::new(span.clone())
+                                        .with_message(message)
+                                        .with_color(Color::Yellow),
+                                )
+                                .with_source(Source::from(reference))
+                                .finish(),
+                        )
+                    }
+                }
+
+                container = None;
+                cases.clear();
+                patterns = false;
+                default = false;
+            }
+            _ => {}
+        }
+    }
+
+    warnings
+}
+
+/// Reports map literals with a key left without a value, or with a key that
+/// is not an atom, string or number, pointing at the literal in the code.
+pub(crate) fn map_literals(code: &[(LToken, Span)], reference: &str) -> Vec<Report> {
+    // The collections open at each point, with where each starts, whether
+    // it is a map and how many items are directly inside it so far.
+    let mut open: Vec<(Span, bool, usize)> = vec![];
+    let mut errors = vec![];
+
+    let error = |message: String, span: Span| {
+        Report::build(ReportKind::Error)
+            .with_message("Invalid Map")
+            .with_label(
+                Label::new(span)
+                    .with_message(message)
+                    .with_color(Color::Red),
+            )
+            .with_source(Source::from(reference))
+            .finish()
+    };
+
+    for (token, span) in code {
+        let (key, opens) = match token {
+            LToken::Token(Token::Special(x)) if matches!(x.as_str(), "}" | "]" | ")") => {
+                if let Some((start, true, count)) = open.pop() {
+                    if count % 2 == 1 {
+                        errors.push(error(
+                            "This map has a key without a value.".to_string(),
+                            start.start..span.end,
+                        ))
+                    }
+                }
+
+                continue;
+            }
+            LToken::Token(Token::Special(x)) => (false, Some(x == "%{")),
+            LToken::Token(token) => (
+                matches!(token, Token::Atom(_) | Token::Str(_) | Token::Value(_)),
+                None,
+            ),
+            LToken::Function(_) | LToken::FunctionLiteral(_) => (false, None),
+            LToken::Newline => {
+                open.clear();
+                continue;
+            }
+            _ => continue,
+        };
+
+        if let Some((_, true, count)) = open.last_mut() {
+            if *count % 2 == 0 && !key {
+                errors.push(error(
+                    "A map can only be keyed by atoms, strings and numbers.".to_string(),
+                    span.clone(),
+                ))
+            }
+
+            *count += 1;
+        }
+
+        if let Some(map) = opens {
+            open.push((span.clone(), map, 0))
+        }
+    }
+
+    errors
+}
 
 pub trait Parser {
     fn parser(
@@ -31,47 +288,187 @@ where
             Ok(tokens) => Ok(tokens[0].default_case.clone()),
             Err(errors) => Err(errors),
         };
-        let mut errors: Vec<Report> = vec![];
-
-        code = code.iter().fold(vec![], |acc, x| {
-            let mut new = acc.clone();
-
-            if acc.is_empty() {
-                new.push(x.clone());
-            } else if let ((LToken::Error, span), (LToken::Error, span_current)) =
-                (acc[acc.len() - 1].clone(), x.clone())
-            {
-                new[acc.len() - 1] = (
-                    LToken::Error,
-                    Span {
-                        start: span.start,
-                        end: span_current.end,
-                    },
+        let mut errors: Vec<Report> = map_literals(&code, reference);
+        let error = |title: &str, message: String, span: Span| {
+            Report::build(ReportKind::Error)
+                .with_message(title)
+                .with_label(
+                    Label::new(span)
+                        .with_message(message)
+                        .with_color(Color::Red),
                 )
-            } else {
-                new.push(x.clone());
+                .with_source(Source::from(reference))
+                .finish()
+        };
+        let mut headers: HashMap<usize, (Vec<String>, Span)> = HashMap::new();
+        let mut header: (Vec<String>, Span) = (vec![], 0..0);
+        let mut extent: Option<Span> = None;
+
+        // Runs of unknown tokens are merged so each is reported once.
+        code = code.into_iter().fold(vec![], |mut acc, x| {
+            match (acc.last_mut(), x) {
+                (Some((LToken::Error, span)), (LToken::Error, span_current)) => {
+                    span.end = span_current.end
+                }
+                (_, x) => acc.push(x),
             }
-            new
+
+            acc
         });
 
         for (token, span) in code {
+            if !matches!(token, LToken::Newline) {
+                extent = Some(extent.map_or(span.clone(), |x| x.start..span.end));
+            }
+
             if let LToken::Newline = token {
+                if current_container.container.is_some() {
+                    headers.insert(parsed.len(), header.clone());
+                }
+
                 if current_container != CodeBlock::default() {
-                    parsed.push(current_container)
+                    parsed.push(CodeBlock {
+                        span: extent.clone().unwrap_or_default(),
+                        ..current_container
+                    })
                 }
 
                 current_container = CodeBlock::default();
-            } else if let LToken::BooleanGuard(name) | LToken::Assignment(name) = token {
-                current_container.container = Some(name)
+                header = (vec![], 0..0);
+                extent = None;
+            } else if let LToken::BooleanGuard(_) | LToken::Assignment(_) | LToken::Signature(_) =
+                token
+            {
+                // Any names before the container's own on the same line are
+                // the parameters it declares, as in `dist a b <- ...`.
+                let declared = current_container.container.is_none()
+                    && current_container.cases.is_none()
+                    && current_container
+                        .default_case
+                        .iter()
+                        .all(|x| matches!(x, Token::Container(_) | Token::Function(_)));
+
+                let mut names = vec![];
+
+                if declared {
+                    for token in current_container.default_case.drain(..) {
+                        if let Token::Container(x) | Token::Function(x) = token {
+                            names.push(x.to_string())
+                        }
+                    }
+                }
+
+                match token {
+                    LToken::BooleanGuard(name) | LToken::Assignment(name) => names.push(name),
+                    LToken::Signature(signature) => {
+                        if names.len() > 1 && names.len() - 1 != signature.parameters.len() {
+                            errors.push(error(
+                                "Mismatched Signature",
+                                format!(
+                                    "{} names {} parameter(s) but its signature has {}.",
+                                    names[0],
+                                    names.len() - 1,
+                                    signature.parameters.len()
+                                ),
+                                span.clone(),
+                            ))
+                        }
+
+                        current_container.signature = Some(signature)
+                    }
+                    _ => unreachable!(),
+                }
+
+                if names.is_empty() {
+                    errors.push(error(
+                        "Invalid Signature",
+                        "A signature must follow the name of the container it describes."
+                            .to_string(),
+                        span,
+                    ));
+                    continue;
+                }
+
+                current_container.container = Some(names.remove(0));
+
+                for name in names.iter().filter(|x| FUNCTIONS.contains_key(x.as_str())) {
+                    errors.push(error(
+                        "Invalid Parameter",
+                        format!(
+                            "{} is a builtin function and can not name a parameter.",
+                            name
+                        ),
+                        span.clone(),
+                    ))
+                }
+
+                header = (names, span);
+            } else if let LToken::Definition(Definition::Record(name, fields)) = token {
+                parsed.push(CodeBlock {
+                    container: Some(name.clone()),
+                    cases: None,
+                    signature: None,
+                    effect: None,
+                    span: span.clone(),
+                    default_case: vec![Token::Record(
+                        name.clone(),
+                        fields
+                            .iter()
+                            .enumerate()
+                            .map(|(index, field)| (field.clone(), Token::Parameter(index)))
+                            .collect(),
+                    )],
+                });
+
+                for field in fields {
+                    parsed.push(CodeBlock {
+                        container: Some(format!("{}_{}", name, field)),
+                        cases: None,
+                        signature: None,
+                        effect: None,
+                        span: span.clone(),
+                        default_case: vec![
+                            Token::Parameter(0),
+                            Token::Atom(field),
+                            Token::Function(Symbol::intern("field")),
+                        ],
+                    });
+                }
+            } else if let LToken::Definition(Definition::Union(name, variants)) = token {
+                for (tag, fields) in variants {
+                    parsed.push(CodeBlock {
+                        container: Some(tag.clone()),
+                        cases: None,
+                        signature: None,
+                        effect: None,
+                        span: span.clone(),
+                        default_case: vec![Token::Variant(
+                            name.clone(),
+                            tag,
+                            (0..fields.len()).map(Token::Parameter).collect(),
+                        )],
+                    });
+                }
             } else if let LToken::GuardOption((x, y)) = token {
-                let mut cases: Vec<(Tokens, Tokens)> = match current_container.cases.clone() {
-                    Some(x) => x,
-                    _ => vec![],
+                let mut cases: Vec<(Tokens, Tokens)> =
+                    current_container.cases.clone().unwrap_or_default();
+
+                let predicate = if pattern::is_pattern(&x) {
+                    match pattern::compile(&x) {
+                        Some(compiled) => Ok(compiled),
+                        None => Err(vec![error(
+                            "Invalid Pattern",
+                            "This guard could not be read as a pattern.".to_string(),
+                            span,
+                        )]),
+                    }
+                } else {
+                    parse(&x).map(|predicate| (predicate, vec![]))
                 };
 
-                match (parse(&x), parse(&y)) {
-                    (Ok(token_x), Ok(token_y)) => {
-                        cases.push((token_x, token_y));
+                match (predicate, parse(&y)) {
+                    (Ok((token_x, bindings)), Ok(token_y)) => {
+                        cases.push((token_x, pattern::bind(token_y, &bindings)));
                         current_container.cases = Some(cases);
                     }
                     (Err(mut result_x), Err(mut result_y)) => {
@@ -86,15 +483,32 @@ where
                     Ok(default_case) => current_container.default_case = default_case,
                     Err(mut results) => errors.append(&mut results),
                 }
+            } else if let LToken::StackEffect(effect) = token {
+                if current_container.container.is_some()
+                    && current_container.default_case.is_empty()
+                    && current_container.cases.is_none()
+                    && current_container.effect.is_none()
+                {
+                    current_container.effect = Some(effect)
+                } else {
+                    errors.push(error(
+                        "Invalid Stack Effect",
+                        "A stack effect can only come straight after a container's arrow."
+                            .to_string(),
+                        span,
+                    ))
+                }
             } else if let LToken::Token(x) = token {
                 current_container.default_case.push(x)
-            } else if let LToken::Assignment(name) = token {
-                current_container.container = Some(name)
             } else if let LToken::Function(func) = token {
-                if FUNCTIONS.get(&func).is_some() {
-                    current_container.default_case.push(Token::Function(func))
+                let symbol = Symbol::intern(&func);
+
+                if symbol.builtin().is_some() {
+                    current_container.default_case.push(Token::Function(symbol))
                 } else {
-                    current_container.default_case.push(Token::Container(func))
+                    current_container
+                        .default_case
+                        .push(Token::Container(symbol))
                 }
             } else if let LToken::FunctionLiteral(func) = token {
                 if FUNCTIONS.get(&func).is_some() {
@@ -107,43 +521,86 @@ where
                         .push(Token::ContainerLiteral(func))
                 }
             } else if let LToken::Error = token {
-                errors.push(
-                    Report::build(ReportKind::Error)
-                        .with_message("Unknown Token")
-                        .with_label(
-                            Label::new(span)
-                                .with_message("The parser can not identify this token.")
-                                .with_color(Color::Red),
-                        )
-                        .with_source(Source::from(reference))
-                        .finish(),
-                )
+                errors.push(error(
+                    "Unknown Token",
+                    "The parser can not identify this token.".to_string(),
+                    span,
+                ))
             }
         }
 
+        if current_container.container.is_some() {
+            headers.insert(parsed.len(), header);
+        }
+
         if current_container != CodeBlock::default() {
-            parsed.push(current_container)
+            parsed.push(CodeBlock {
+                span: extent.unwrap_or_default(),
+                ..current_container
+            })
+        }
+
+        let known = parsed
+            .iter()
+            .filter_map(|code_block| code_block.container.clone())
+            .chain(self.keys().map(Symbol::to_string))
+            .collect::<Vec<_>>();
+
+        let bundled = parsed
+            .par_iter()
+            .cloned()
+            .map(|code_block| CodeBlock {
+                container: code_block.container,
+                cases: code_block.cases.map(|inner| {
+                    inner
+                        .iter()
+                        .map(|(predictate, consequent)| (predictate.bundle(), consequent.bundle()))
+                        .collect::<Vec<_>>()
+                }),
+                default_case: code_block.default_case.bundle(),
+                signature: code_block.signature,
+                effect: code_block.effect,
+                span: code_block.span,
+            })
+            .collect::<Vec<_>>();
+
+        let mut scoped = vec![];
+
+        for (index, code_block) in bundled.into_iter().enumerate() {
+            let (names, span) = match headers.get(&index) {
+                Some(header) => header,
+                None => {
+                    scoped.push(code_block);
+                    continue;
+                }
+            };
+
+            match scope::compile(code_block, names) {
+                Ok((mut blocks, unbound)) => {
+                    if !names.is_empty() {
+                        for name in unbound.iter().filter(|x| !known.contains(x)) {
+                            errors.push(error(
+                                "Undeclared Name",
+                                format!(
+                                    "{} is neither a parameter of {} nor a known container.",
+                                    name,
+                                    blocks[0].container.clone().unwrap_or_default()
+                                ),
+                                span.clone(),
+                            ))
+                        }
+                    }
+
+                    scoped.append(&mut blocks)
+                }
+                Err((title, message)) => errors.push(error(title, message, span.clone())),
+            }
         }
 
         if !errors.is_empty() {
             Err(errors)
         } else {
-            Ok(parsed
-                .par_iter()
-                .cloned()
-                .map(|code_block| CodeBlock {
-                    container: code_block.container,
-                    cases: code_block.cases.map(|inner| {
-                        inner
-                            .iter()
-                            .map(|(predictate, consequent)| {
-                                (predictate.bundle(), consequent.bundle())
-                            })
-                            .collect::<Vec<_>>()
-                    }),
-                    default_case: code_block.default_case.bundle(),
-                })
-                .collect::<Vec<_>>())
+            Ok(scoped)
         }
     }
 }
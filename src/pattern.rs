@@ -0,0 +1,396 @@
+use crate::{Symbol, Token, Tokens};
+use std::iter::Peekable;
+use std::slice;
+use std::vec::IntoIter;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    Wildcard,
+    Binding(String),
+    As(String, Box<Pattern>),
+    Literal(Token),
+    Type(String, Vec<Pattern>),
+    Variant(String, Vec<Pattern>),
+    Sequence(String, Vec<Pattern>, Option<Box<Pattern>>),
+}
+
+pub type Bindings = Vec<(String, Tokens)>;
+
+pub fn is_pattern(predicate: &str) -> bool {
+    predicate.trim_start().starts_with('(')
+}
+
+fn split(text: &str) -> Vec<String> {
+    let mut pieces = vec![];
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+
+    for character in text.chars() {
+        if let Some(x) = quote {
+            current.push(character);
+
+            if character == x {
+                quote = None;
+            }
+        } else if character == '"' || character == '\'' {
+            current.push(character);
+            quote = Some(character);
+        } else if character.is_whitespace() || "()[]{}".contains(character) {
+            if !current.is_empty() {
+                pieces.push(current.clone());
+                current.clear();
+            }
+
+            if !character.is_whitespace() {
+                pieces.push(character.to_string());
+            }
+        } else {
+            current.push(character);
+        }
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+fn is_name(piece: &str) -> bool {
+    !piece.is_empty() && piece.chars().all(|x| x.is_ascii_alphabetic() || x == '_')
+}
+
+fn is_type(piece: &str) -> bool {
+    is_name(piece) && piece.starts_with(|x: char| x.is_ascii_uppercase())
+}
+
+fn literal(piece: &str) -> Option<Token> {
+    if let Ok(x) = piece.parse::<f64>() {
+        Some(Token::Value(x))
+    } else if let Some(x) = piece.strip_prefix(':').filter(|x| !x.is_empty()) {
+        Some(Token::Atom(x.to_string()))
+    } else if piece.len() > 1 && piece.starts_with('"') && piece.ends_with('"') {
+        Some(Token::Str(piece[1..piece.len() - 1].to_string()))
+    } else if piece.len() == 3 && piece.starts_with('\'') && piece.ends_with('\'') {
+        piece.chars().nth(1).map(Token::Char)
+    } else {
+        None
+    }
+}
+
+fn parse_until(pieces: &mut Peekable<IntoIter<String>>, end: &str) -> Option<Vec<Pattern>> {
+    let mut patterns = vec![];
+
+    while pieces.peek()? != end {
+        patterns.push(parse(pieces)?);
+    }
+
+    pieces.next();
+    Some(patterns)
+}
+
+fn parse_sequence(
+    pieces: &mut Peekable<IntoIter<String>>,
+    kind: &str,
+    end: &str,
+) -> Option<Pattern> {
+    let mut items = vec![];
+    let mut rest = None;
+
+    while pieces.peek()? != end {
+        if let Some(name) = pieces.peek()?.strip_prefix("...").map(str::to_string) {
+            pieces.next();
+            rest = Some(Box::new(match name.as_str() {
+                "" | "_" => Pattern::Wildcard,
+                _ if is_name(&name) => Pattern::Binding(name),
+                _ => return None,
+            }));
+
+            if pieces.peek()? != end {
+                return None;
+            }
+        } else {
+            items.push(parse(pieces)?);
+        }
+    }
+
+    pieces.next();
+    Some(Pattern::Sequence(kind.to_string(), items, rest))
+}
+
+fn parse(pieces: &mut Peekable<IntoIter<String>>) -> Option<Pattern> {
+    let piece = pieces.next()?;
+
+    if piece == "(" {
+        let first = pieces.peek()?.clone();
+
+        if let Some(tag) = first.strip_prefix(':') {
+            pieces.next();
+            Some(Pattern::Variant(tag.to_string(), parse_until(pieces, ")")?))
+        } else if is_type(&first) {
+            pieces.next();
+            Some(Pattern::Type(first, parse_until(pieces, ")")?))
+        } else {
+            let pattern = parse(pieces)?;
+            (pieces.next()? == ")").then_some(pattern)
+        }
+    } else if piece == "[" {
+        parse_sequence(pieces, "List", "]")
+    } else if piece == "{" {
+        parse_sequence(pieces, "Group", "}")
+    } else if piece == "_" {
+        Some(Pattern::Wildcard)
+    } else if let Some((name, rest)) = piece.split_once('@') {
+        let pattern = if rest.is_empty() {
+            parse(pieces)?
+        } else {
+            parse(&mut vec![rest.to_string()].into_iter().peekable())?
+        };
+
+        is_name(name).then(|| Pattern::As(name.to_string(), Box::new(pattern)))
+    } else if is_type(&piece) {
+        Some(Pattern::Type(piece, vec![]))
+    } else if is_name(&piece) {
+        Some(Pattern::Binding(piece))
+    } else {
+        literal(&piece).map(Pattern::Literal)
+    }
+}
+
+fn call(subject: &Tokens, arguments: Tokens, function: &str) -> Tokens {
+    [
+        subject.clone(),
+        arguments,
+        vec![Token::Function(Symbol::intern(function))],
+    ]
+    .concat()
+}
+
+fn compile_pattern(
+    pattern: &Pattern,
+    subject: Tokens,
+    predicates: &mut Vec<Tokens>,
+    bindings: &mut Bindings,
+) {
+    match pattern {
+        Pattern::Wildcard => {}
+        Pattern::Binding(name) => bindings.push((name.to_string(), subject)),
+        Pattern::As(name, pattern) => {
+            bindings.push((name.to_string(), subject.clone()));
+            compile_pattern(pattern, subject, predicates, bindings);
+        }
+        Pattern::Literal(token) => predicates.push(call(&subject, vec![token.clone()], "eq")),
+        Pattern::Type(name, fields) => {
+            predicates.push(call(
+                &call(&subject, vec![], "type"),
+                vec![Token::Str(name.to_string())],
+                "eq",
+            ));
+
+            for (index, field) in fields.iter().enumerate() {
+                compile_pattern(
+                    field,
+                    call(&subject, vec![Token::Value(index as f64)], "element"),
+                    predicates,
+                    bindings,
+                );
+            }
+        }
+        Pattern::Variant(tag, fields) => {
+            let tag = vec![Token::Atom(tag.to_string())];
+            let variant = call(&subject, tag.clone(), "is_variant");
+
+            // A tag without fields also matches the bare atom of that name.
+            predicates.push(if fields.is_empty() {
+                [
+                    variant,
+                    call(&subject, tag, "eq"),
+                    vec![Token::Function(Symbol::intern("or"))],
+                ]
+                .concat()
+            } else {
+                variant
+            });
+
+            for (index, field) in fields.iter().enumerate() {
+                compile_pattern(
+                    field,
+                    call(&subject, vec![Token::Value(index as f64)], "payload"),
+                    predicates,
+                    bindings,
+                );
+            }
+        }
+        Pattern::Sequence(kind, items, rest) => {
+            let length = vec![Token::Value(items.len() as f64)];
+
+            predicates.push(call(
+                &call(&subject, vec![], "type"),
+                vec![Token::Str(kind.to_string())],
+                "eq",
+            ));
+            predicates.push(match rest {
+                Some(_) => call(&subject, length, "has_min_length"),
+                None => call(&subject, length, "has_length"),
+            });
+
+            for (index, item) in items.iter().enumerate() {
+                compile_pattern(
+                    item,
+                    call(&subject, vec![Token::Value(index as f64)], "element"),
+                    predicates,
+                    bindings,
+                );
+            }
+
+            if let Some(rest) = rest {
+                compile_pattern(
+                    rest,
+                    call(
+                        &subject,
+                        vec![Token::Value(items.len() as f64)],
+                        "elements_from",
+                    ),
+                    predicates,
+                    bindings,
+                );
+            }
+        }
+    }
+}
+
+/// Compiles a guard made of one parenthesised pattern per parameter, such
+/// as `(:some x) ([h ...t])`, into an ordinary predicate along with the
+/// tokens that extract each bound name from the parameters.
+pub fn compile(text: &str) -> Option<(Tokens, Bindings)> {
+    let mut pieces = split(text).into_iter().peekable();
+    let mut predicates = vec![];
+    let mut bindings = vec![];
+    let mut index = 0;
+
+    while pieces.peek().is_some() {
+        if pieces.peek()? != "(" {
+            return None;
+        }
+
+        compile_pattern(
+            &parse(&mut pieces)?,
+            vec![Token::Parameter(index)],
+            &mut predicates,
+            &mut bindings,
+        );
+        index += 1;
+    }
+
+    let predicate = predicates
+        .into_iter()
+        .enumerate()
+        .fold(vec![], |acc, (index, predicate)| {
+            if index == 0 {
+                predicate
+            } else {
+                [acc, predicate, vec![Token::Function(Symbol::intern("and"))]].concat()
+            }
+        });
+
+    if predicate.is_empty() {
+        Some((vec![Token::Value(1.0)], bindings))
+    } else {
+        Some((predicate, bindings))
+    }
+}
+
+/// Whether a name is used at any depth inside the collections of a body.
+fn nested(tokens: &[Token], name: &str) -> bool {
+    let mentions = |tokens: &[Token]| {
+        tokens.iter().any(|token| match token {
+            Token::Container(x) => x.as_str() == name,
+            _ => nested(slice::from_ref(token), name),
+        })
+    };
+
+    tokens.iter().any(|token| match token {
+        Token::Group(inner) | Token::List(inner) | Token::Lambda(inner) => mentions(inner),
+        Token::Record(_, fields) => fields.iter().any(|(_, x)| mentions(slice::from_ref(x))),
+        _ => false,
+    })
+}
+
+/// What the pattern for one parameter is sure to match, as far as telling
+/// whether a container's cases cover every variant of a union, or every
+/// length of a list or group, goes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Cover {
+    Anything,
+    Variant(String, bool),
+    Sequence(String, usize, bool),
+    Other,
+}
+
+fn irrefutable(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) => true,
+        Pattern::As(_, pattern) => irrefutable(pattern),
+        _ => false,
+    }
+}
+
+fn cover(pattern: &Pattern) -> Cover {
+    match pattern {
+        _ if irrefutable(pattern) => Cover::Anything,
+        Pattern::As(_, pattern) => cover(pattern),
+        Pattern::Variant(tag, fields) => {
+            Cover::Variant(tag.to_string(), fields.iter().all(irrefutable))
+        }
+        Pattern::Sequence(kind, items, rest) if items.iter().all(irrefutable) => {
+            Cover::Sequence(kind.to_string(), items.len(), rest.is_some())
+        }
+        _ => Cover::Other,
+    }
+}
+
+/// What each parameter's pattern in a guard is sure to match, where a tag
+/// comes along with whether every value of that variant matches, and a list
+/// or group along with its length and whether it may be longer.
+pub(crate) fn covers(text: &str) -> Option<Vec<Cover>> {
+    let mut pieces = split(text).into_iter().peekable();
+    let mut covers = vec![];
+
+    while pieces.peek().is_some() {
+        if pieces.peek()? != "(" {
+            return None;
+        }
+
+        covers.push(cover(&parse(&mut pieces)?));
+    }
+
+    Some(covers)
+}
+
+/// Replaces each bound name in a consequent with the tokens extracting it.
+/// A name used inside a collection is bound with `let` instead, so that the
+/// collection is given its value rather than the tokens that extract it.
+pub fn bind(tokens: Tokens, bindings: &Bindings) -> Tokens {
+    let (bound, spliced): (Vec<_>, Vec<_>) =
+        bindings.iter().partition(|(name, _)| nested(&tokens, name));
+
+    let lets = bound.into_iter().flat_map(|(name, subject)| {
+        [
+            subject.clone(),
+            vec![
+                Token::Container(Symbol::intern("let")),
+                Token::Container(Symbol::intern(name)),
+            ],
+        ]
+        .concat()
+    });
+
+    lets.chain(tokens.into_iter().flat_map(|token| match token {
+        Token::Container(name) => match spliced.iter().find(|(x, _)| x == name.as_str()) {
+            Some((_, subject)) => subject.clone(),
+            None => vec![token],
+        },
+        _ => vec![token],
+    }))
+    .collect()
+}
@@ -0,0 +1,306 @@
+use crate::{CodeBlock, Symbol, Token, Tokens};
+use std::slice;
+
+pub type ScopeError = (&'static str, String);
+
+fn is_let(token: &Token) -> bool {
+    matches!(token, Token::Container(x) if x.as_str() == "let")
+}
+
+/// The tokens of a body along with those at any depth inside the groups,
+/// lists and records it makes, which is as far as the body's own parameters reach.
+fn reachable(tokens: &[Token]) -> Vec<&Token> {
+    tokens
+        .iter()
+        .flat_map(|token| match token {
+            Token::Group(inner) | Token::List(inner) | Token::Lambda(inner) => reachable(inner),
+            Token::Record(_, fields) => fields
+                .iter()
+                .flat_map(|(_, x)| reachable(slice::from_ref(x)))
+                .collect(),
+            _ => vec![token],
+        })
+        .collect()
+}
+
+/// Rebuilds a body, mapping its tokens and those at any depth inside its
+/// groups, lists and records, the latter being told apart so that names there can
+/// become captures. Nested tokens are given the index of the collection at
+/// the top of the body they are in.
+fn rebuild(tokens: Tokens, mut f: impl FnMut(usize, Token, bool) -> Token) -> Tokens {
+    fn map(
+        token: Token,
+        index: usize,
+        nested: bool,
+        f: &mut impl FnMut(usize, Token, bool) -> Token,
+    ) -> Token {
+        let mut inner = |tokens: Tokens| -> Tokens {
+            tokens
+                .into_iter()
+                .map(|token| map(token, index, true, f))
+                .collect()
+        };
+
+        match token {
+            Token::Group(tokens) => Token::Group(inner(tokens)),
+            Token::List(tokens) => Token::List(inner(tokens)),
+            Token::Lambda(tokens) => Token::Lambda(inner(tokens)),
+            Token::Record(name, fields) => Token::Record(
+                name,
+                fields
+                    .into_iter()
+                    .map(|(field, x)| (field, map(x, index, true, f)))
+                    .collect(),
+            ),
+            _ => f(index, token, nested),
+        }
+    }
+
+    tokens
+        .into_iter()
+        .enumerate()
+        .map(|(index, token)| map(token, index, false, &mut f))
+        .collect()
+}
+
+/// The parameters a body uses, leaving out the `$n` of the lambdas it makes.
+fn parameters(tokens: &Tokens) -> Vec<usize> {
+    let mut indices = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Parameter(index) => Some(*index),
+            _ => None,
+        })
+        .chain(
+            reachable(tokens)
+                .into_iter()
+                .filter_map(|token| match token {
+                    Token::Capture(index) => Some(*index),
+                    _ => None,
+                }),
+        )
+        .collect::<Vec<_>>();
+
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+fn declare(tokens: Tokens, names: &[String]) -> Tokens {
+    rebuild(tokens, |_, token, nested| match token {
+        Token::Container(name) => match names.iter().position(|x| x == name.as_str()) {
+            Some(index) if nested => Token::Capture(index),
+            Some(index) => Token::Parameter(index),
+            None => token,
+        },
+        _ => token,
+    })
+}
+
+fn validate(
+    tokens: &Tokens,
+    container: &str,
+    names: &[String],
+    arity: Option<usize>,
+) -> Result<(), ScopeError> {
+    for (index, token) in tokens.iter().enumerate() {
+        match (token, tokens.get(index + 1), arity) {
+            (Token::Parameter(x), _, Some(arity)) if *x >= arity => {
+                return Err((
+                    "Undeclared Parameter",
+                    format!(
+                        "{} declares {} parameter(s), so ${} does not refer to any of them.",
+                        container, arity, x
+                    ),
+                ))
+            }
+            (x, Some(Token::Container(name)), _)
+                if is_let(x) && names.iter().any(|x| x == name.as_str()) =>
+            {
+                return Err((
+                    "Invalid Binding",
+                    format!("{} is already a parameter of {}.", name, container),
+                ))
+            }
+            (x, Some(Token::Container(name)), _) if is_let(x) && name.as_str() != "let" => {}
+            (x, _, _) if is_let(x) => {
+                return Err((
+                    "Invalid Binding",
+                    "let must be followed by the name it binds.".to_string(),
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    for token in reachable(tokens) {
+        match (token, arity) {
+            (Token::Capture(x), Some(arity)) if *x >= arity => {
+                return Err((
+                    "Undeclared Parameter",
+                    format!(
+                        "{} declares {} parameter(s), so ^{} does not refer to any of them.",
+                        container, arity, x
+                    ),
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves everything after the first `let x` into a hidden container which
+/// takes the bound value followed by the parameters the rest still uses, so
+/// that `x` becomes an ordinary parameter of it.
+fn lift(tokens: Tokens, container: &str, lifted: &mut Vec<CodeBlock>) -> Tokens {
+    let position = match tokens.iter().position(is_let) {
+        Some(position) => position,
+        None => return tokens,
+    };
+
+    let name = tokens[position + 1].clone();
+    let before = tokens[..position].to_vec();
+    let after = tokens[position + 2..].to_vec();
+
+    // A later `let` of the same name shadows this one from then on.
+    let shadowed = after
+        .windows(2)
+        .position(|x| is_let(&x[0]) && x[1] == name)
+        .map_or(after.len(), |x| x + 1);
+
+    if !reachable(&after[..shadowed])
+        .into_iter()
+        .any(|x| *x == name)
+    {
+        return [
+            before,
+            vec![Token::Function(Symbol::intern("axe"))],
+            lift(after, container, lifted),
+        ]
+        .concat();
+    }
+
+    let captured = parameters(&after);
+    let body = rebuild(after, |index, token, nested| match token {
+        _ if index < shadowed && token == name && nested => Token::Capture(0),
+        _ if index < shadowed && token == name => Token::Parameter(0),
+        Token::Parameter(x) if !nested => Token::Parameter(captured.binary_search(&x).unwrap() + 1),
+        Token::Capture(x) => Token::Capture(captured.binary_search(&x).unwrap() + 1),
+        _ => token,
+    });
+
+    let hidden = format!("{}#let{}", container, lifted.len());
+    let index = lifted.len();
+
+    lifted.push(CodeBlock {
+        container: Some(hidden.clone()),
+        cases: None,
+        default_case: vec![],
+        signature: None,
+        effect: None,
+        span: 0..0,
+    });
+    lifted[index].default_case = lift(body, container, lifted);
+
+    [
+        before,
+        captured.into_iter().map(Token::Parameter).collect(),
+        vec![Token::Container(Symbol::intern(&hidden))],
+    ]
+    .concat()
+}
+
+fn free(tokens: &Tokens, names: &mut Vec<String>) {
+    for token in tokens {
+        match token {
+            Token::Container(name)
+                if !name.as_str().contains('#') && !names.iter().any(|x| x == name.as_str()) =>
+            {
+                names.push(name.to_string())
+            }
+            Token::Group(inner) | Token::List(inner) | Token::Lambda(inner) => free(inner, names),
+            _ => {}
+        }
+    }
+}
+
+/// Compiles a container's named parameters and `let` bindings down to
+/// positional parameters, giving back the container along with the hidden
+/// containers its bindings were lifted into and the names it leaves free.
+pub fn compile(
+    block: CodeBlock,
+    names: &[String],
+) -> Result<(Vec<CodeBlock>, Vec<String>), ScopeError> {
+    let container = block.container.clone().unwrap_or_default();
+
+    if let Some(name) = names
+        .iter()
+        .enumerate()
+        .find(|(index, name)| names[..*index].contains(name))
+        .map(|(_, name)| name)
+    {
+        return Err((
+            "Invalid Parameter",
+            format!(
+                "{} declares the parameter {} more than once.",
+                container, name
+            ),
+        ));
+    }
+
+    let mut cases = block.cases.clone().unwrap_or_default();
+    let arity = match &block.signature {
+        Some(signature) => Some(signature.parameters.len()),
+        None => (!names.is_empty()).then_some(names.len()),
+    };
+
+    for tokens in cases
+        .iter()
+        .flat_map(|(x, y)| [x, y])
+        .chain([&block.default_case])
+    {
+        validate(tokens, &container, names, arity)?;
+    }
+
+    let mut lifted = vec![];
+
+    cases = cases
+        .into_iter()
+        .map(|(predicate, consequent)| {
+            (
+                lift(declare(predicate, names), &container, &mut lifted),
+                lift(declare(consequent, names), &container, &mut lifted),
+            )
+        })
+        .collect();
+
+    let compiled = CodeBlock {
+        container: block.container,
+        cases: block.cases.map(|_| cases),
+        default_case: lift(declare(block.default_case, names), &container, &mut lifted),
+        signature: block.signature,
+        effect: block.effect,
+        span: block.span,
+    };
+
+    // Code lifted out of the container is reported against where the
+    // container itself was written.
+    for hidden in &mut lifted {
+        hidden.span = compiled.span.clone();
+    }
+
+    let mut unbound = vec![];
+
+    for block in [&compiled].into_iter().chain(lifted.iter()) {
+        for (predicate, consequent) in block.cases.iter().flatten() {
+            free(predicate, &mut unbound);
+            free(consequent, &mut unbound);
+        }
+
+        free(&block.default_case, &mut unbound);
+    }
+
+    Ok(([vec![compiled], lifted].concat(), unbound))
+}
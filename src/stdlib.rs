@@ -1,19 +1,36 @@
 use crate::evaluator::Evaluate;
-use crate::utils::{convert, encode_string, type_of, Utils};
-use crate::{FunctionRef, State, Token, Token::*, Tokens};
-use charon_ariadne::Report;
-use itertools::Itertools;
+use crate::utils::{
+    compare, convert, encode_set, encode_string, equal, has_type, insert_pair, type_of, Utils,
+};
+use crate::{FunctionRef, FunctionRef::*, State, Symbol, Token, Token::*, Tokens};
+use charon_ariadne::{Color, Label, Report, ReportKind, Source};
 use phf::phf_map;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+/// How long an iterator must be before map shares it between threads, when
+/// that has been turned on.
+const PARALLEL_THRESHOLD: usize = 256;
 
 fn type_of_container(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     Ok(encode_string(&type_of(&par[0])))
 }
 
 fn sum(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    // A string is Iterable for the builtins that take it apart, but its
+    // characters can not be added up.
     let x = if let Group(x) | List(x) = &par[0] {
         x
     } else {
-        unimplemented!()
+        return Err(parameter_error(
+            &par,
+            0,
+            "Mismatched Types",
+            format!(
+                "This has the type of {} but expected Group or List.",
+                type_of(&par[0])
+            ),
+        ));
     };
 
     Ok(Value(x.as_nums().iter().sum()))
@@ -71,12 +88,18 @@ fn modulo(_state: &mut State, par: Tokens) -> Result<Token, Report> {
 
 fn len(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     let x = if let Group(x) | List(x) = &par[0] {
-        x
+        x.len()
+    } else if let Map(x) = &par[0] {
+        x.len()
+    } else if let Set(x) = &par[0] {
+        x.len()
+    } else if let Str(x) = &par[0] {
+        x.chars().count()
     } else {
         unimplemented!()
     };
 
-    Ok(Value(x.len() as f64))
+    Ok(Value(x as f64))
 }
 
 fn reverse(_state: &mut State, par: Tokens) -> Result<Token, Report> {
@@ -84,6 +107,8 @@ fn reverse(_state: &mut State, par: Tokens) -> Result<Token, Report> {
         Ok(Group(x.iter().rev().cloned().collect::<Vec<_>>()))
     } else if let List(x) = &par[0] {
         Ok(List(x.iter().rev().cloned().collect::<Vec<_>>()))
+    } else if let Str(x) = &par[0] {
+        Ok(Str(x.chars().rev().collect()))
     } else {
         unimplemented!()
     }
@@ -95,22 +120,19 @@ fn output(_state: &mut State, par: Tokens) -> Result<Token, Report> {
 }
 
 fn eq(_state: &mut State, par: Tokens) -> Result<Token, Report> {
-    let parameters: (Token, Token) = par.iter().cloned().collect_tuple().unwrap();
-
-    let equal = match parameters {
-        (Value(x), Value(y)) => x == y,
-        (Function(x), Function(y)) | (FunctionLiteral(x), FunctionLiteral(y)) => x == y,
-        (Container(x), Container(y))
-        | (Atom(x), Atom(y))
-        | (ContainerLiteral(x), ContainerLiteral(y)) => x == y,
-        (Parameter(x), Parameter(y)) => x == y,
-        _ => unimplemented!(),
-    };
+    Ok(if equal(&par[1], &par[0]) {
+        Value(1.0)
+    } else {
+        Value(0.0)
+    })
+}
 
-    Ok(Value(match equal {
-        true => 1.0,
-        false => 0.0,
-    }))
+fn neq(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(if !equal(&par[1], &par[0]) {
+        Value(1.0)
+    } else {
+        Value(0.0)
+    })
 }
 
 fn or(_state: &mut State, par: Tokens) -> Result<Token, Report> {
@@ -151,54 +173,298 @@ fn and(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     })
 }
 
+// `greater` and `less` test the top of the stack against the value beneath
+// it, as they always have, whereas the operators below read left to right.
+
 fn greater(_state: &mut State, par: Tokens) -> Result<Token, Report> {
-    let (x, y) = if let [Value(x), Value(y)] = par.as_slice() {
-        (x, y)
+    Ok(if compare(&par[0], &par[1]) == Ordering::Greater {
+        Value(1.0)
     } else {
-        unimplemented!()
-    };
-
-    Ok(if *x > *y { Value(1.0) } else { Value(0.0) })
+        Value(0.0)
+    })
 }
 
 fn less(_state: &mut State, par: Tokens) -> Result<Token, Report> {
-    let (x, y) = if let [Value(x), Value(y)] = par.as_slice() {
-        (x, y)
+    Ok(if compare(&par[0], &par[1]) == Ordering::Less {
+        Value(1.0)
     } else {
-        unimplemented!()
-    };
+        Value(0.0)
+    })
+}
+
+fn greater_than(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(if compare(&par[1], &par[0]) == Ordering::Greater {
+        Value(1.0)
+    } else {
+        Value(0.0)
+    })
+}
+
+fn less_than(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(if compare(&par[1], &par[0]) == Ordering::Less {
+        Value(1.0)
+    } else {
+        Value(0.0)
+    })
+}
+
+fn greater_eq(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(if compare(&par[1], &par[0]) != Ordering::Less {
+        Value(1.0)
+    } else {
+        Value(0.0)
+    })
+}
+
+fn less_eq(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(if compare(&par[1], &par[0]) != Ordering::Greater {
+        Value(1.0)
+    } else {
+        Value(0.0)
+    })
+}
 
-    Ok(if x < y { Value(1.0) } else { Value(0.0) })
+fn compare_container(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Value(match compare(&par[1], &par[0]) {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    }))
 }
 
 fn axe(_state: &mut State, _: Tokens) -> Result<Token, Report> {
     Ok(Void)
 }
 
+// Those which shuffle the stack are given their parameters top first, as every
+// builtin is, and give back what they leave in their place bottom first.
+
+fn over(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    Ok(vec![par[1].clone(), par[0].clone(), par[1].clone()])
+}
+
+fn rot(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    Ok(vec![par[1].clone(), par[0].clone(), par[2].clone()])
+}
+
+fn unrot(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    Ok(vec![par[0].clone(), par[2].clone(), par[1].clone()])
+}
+
+fn tuck(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    Ok(vec![par[0].clone(), par[1].clone(), par[0].clone()])
+}
+
+fn two_dup(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    Ok(vec![
+        par[1].clone(),
+        par[0].clone(),
+        par[1].clone(),
+        par[0].clone(),
+    ])
+}
+
+/// The rest of the stack given to a builtin which takes all of it, bottom
+/// first, along with where its nth value from the top is found.
+fn rest_of(par: &Tokens) -> Result<(Tokens, usize), Report> {
+    let stack = par[1..].iter().rev().cloned().collect::<Tokens>();
+    let len = stack.len();
+
+    match par[0] {
+        Value(x) => match index_of(x, len) {
+            Some(index) => Ok((stack, len - 1 - index)),
+            None => Err(out_of_bounds(par, 0, len)),
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn pick(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    let (mut stack, position) = rest_of(&par)?;
+
+    stack.push(stack[position].clone());
+    Ok(stack)
+}
+
+fn roll(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    let (mut stack, position) = rest_of(&par)?;
+    let value = stack.remove(position);
+
+    stack.push(value);
+    Ok(stack)
+}
+
+fn depth(_state: &mut State, par: Tokens) -> Result<Tokens, Report> {
+    let len = par.len();
+
+    Ok([par.into_iter().rev().collect(), vec![Value(len as f64)]].concat())
+}
+
 fn call(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     Ok(match &par[0] {
-        ContainerLiteral(x) => Container(x.to_string()),
-        FunctionLiteral(x) => Function(x.to_string()),
+        ContainerLiteral(x) => Container(Symbol::intern(x)),
+        FunctionLiteral(x) => Function(Symbol::intern(x)),
         Lambda(lambda) => ActiveLambda(lambda.to_vec()),
         _ => unimplemented!(),
     })
 }
 
-fn map(_state: &mut State, par: Tokens) -> Result<Token, Report> {
-    let deref = &call(_state, vec![par[0].clone()])?;
+/// The tokens that call a literal from within the body of another lambda.
+fn invoke(state: &mut State, literal: &Token) -> Result<Tokens, Report> {
+    Ok(match literal {
+        Lambda(_) => vec![literal.clone(), Function(Symbol::intern("call"))],
+        _ => vec![call(state, vec![literal.clone()])?],
+    })
+}
+
+fn curry(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Lambda(
+        [vec![par[1].clone()], invoke(_state, &par[0])?].concat(),
+    ))
+}
+
+/// Binds as many values from beneath the literal as asked, by giving back a
+/// lambda to run which takes them and captures each in the one it makes.
+fn partial(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let count = match par[0] {
+        Value(x) if x >= 0.0 && x.fract() == 0.0 => x as usize,
+        _ => {
+            return Err(parameter_error(
+                &par,
+                0,
+                "Invalid Count",
+                "This is not a whole number of values to bind.".to_string(),
+            ))
+        }
+    };
+
+    Ok(ActiveLambda(vec![Lambda(
+        [(0..count).map(Capture).collect(), invoke(_state, &par[1])?].concat(),
+    )]))
+}
+
+fn compose(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Lambda(
+        [invoke(_state, &par[1])?, invoke(_state, &par[0])?].concat(),
+    ))
+}
+
+fn flip(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Lambda(
+        [vec![Parameter(1), Parameter(0)], invoke(_state, &par[0])?].concat(),
+    ))
+}
+
+fn constant(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Lambda(vec![
+        Parameter(0),
+        Function(Symbol::intern("axe")),
+        par[0].clone(),
+    ]))
+}
+
+/// Whether running these tokens could only change the stack, so that they can
+/// be run on several threads without output coming out of order.
+fn is_pure(state: &State, tokens: &[Token], seen: &mut Vec<Symbol>) -> bool {
+    tokens.iter().all(|token| match token {
+        Function(x) => x.as_str() != "OUTPUT",
+        FunctionLiteral(x) => x != "OUTPUT",
+        Container(x) => is_pure_container(state, *x, seen),
+        ContainerLiteral(x) => is_pure_container(state, Symbol::intern(x), seen),
+        Lambda(x) | ActiveLambda(x) | Group(x) | List(x) => is_pure(state, x, seen),
+        _ => true,
+    })
+}
+
+fn is_pure_container(state: &State, name: Symbol, seen: &mut Vec<Symbol>) -> bool {
+    if seen.contains(&name) {
+        return true;
+    }
+
+    seen.push(name);
+
+    match state.get(&name) {
+        Some(container) => container
+            .cases
+            .iter()
+            .all(|(x, y)| is_pure(state, x, seen) && is_pure(state, y, seen)),
+        None => false,
+    }
+}
 
-    let elements: Vec<Token> = if let Iterator(x) = &par[1] {
+/// Runs `func` on each element, giving back what it left on the stack each
+/// time. When asked to and `func` is pure, the elements are shared between
+/// threads which each evaluate against their own handle on the state, all of
+/// them sharing its containers.
+fn each(
+    state: &mut State,
+    func: &Token,
+    elements: Tokens,
+    parallel: bool,
+) -> Result<Vec<Tokens>, Report> {
+    if parallel && is_pure(state, std::slice::from_ref(func), &mut vec![]) {
+        let shared: &State = state;
+
+        return elements
+            .into_par_iter()
+            .map_init(
+                || shared.clone(),
+                |worker, element| worker.eval(vec![element, func.clone()]),
+            )
+            .collect();
+    }
+
+    elements
+        .into_iter()
+        .map(|element| state.eval(vec![element, func.clone()]))
+        .collect()
+}
+
+fn elements(iterator: &Token) -> Tokens {
+    if let Iterator(x) = iterator {
         x.to_vec()
     } else {
         unimplemented!()
-    };
-
-    let mut result = vec![];
-    for element in elements {
-        result = [result, _state.eval(vec![element, deref.clone()])?].concat();
     }
+}
+
+fn map(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let deref = &call(_state, vec![par[0].clone()])?;
+    let elements = elements(&par[1]);
+    let parallel = _state.parallel() && elements.len() >= PARALLEL_THRESHOLD;
+
+    Ok(Iterator(
+        each(_state, deref, elements, parallel)?
+            .into_iter()
+            .flatten()
+            .collect(),
+    ))
+}
+
+fn par_map(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let deref = &call(_state, vec![par[0].clone()])?;
+
+    Ok(Iterator(
+        each(_state, deref, elements(&par[1]), true)?
+            .into_iter()
+            .flatten()
+            .collect(),
+    ))
+}
+
+fn par_filter(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let deref = &call(_state, vec![par[0].clone()])?;
+    let elements = elements(&par[1]);
+    let kept = each(_state, deref, elements.clone(), true)?;
 
-    Ok(Iterator(result))
+    Ok(Iterator(
+        elements
+            .into_iter()
+            .zip(kept)
+            .filter(|(_, result)| *result == vec![Value(1.0)])
+            .map(|(element, _)| element)
+            .collect(),
+    ))
 }
 
 fn foldr(_state: &mut State, par: Tokens) -> Result<Token, Report> {
@@ -228,11 +494,7 @@ fn foldl(_state: &mut State, mut par: Tokens) -> Result<Token, Report> {
 }
 
 fn iter(_state: &mut State, par: Tokens) -> Result<Token, Report> {
-    Ok(if let Group(x) | List(x) = &par[0] {
-        Iterator(x.to_vec())
-    } else {
-        unimplemented!()
-    })
+    Ok(Iterator(contents(&par[0])))
 }
 
 fn collect_group(_state: &mut State, par: Tokens) -> Result<Token, Report> {
@@ -245,6 +507,16 @@ fn collect_group(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     Ok(Group(arr))
 }
 
+fn collect_string(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let arr = if let Iterator(x) = &par[0] {
+        x.to_vec()
+    } else {
+        unimplemented!()
+    };
+
+    Ok(Str(arr.iter().map(text).collect()))
+}
+
 fn collect_list(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     let arr = if let Iterator(x) = &par[0] {
         x.to_vec()
@@ -265,11 +537,13 @@ fn lambda(_state: &mut State, par: Tokens) -> Result<Token, Report> {
 
 fn head(_state: &mut State, par: Tokens) -> Result<Token, Report> {
     if let Group(x) | List(x) = &par[0] {
-        if let Some(first) = x.get(0) {
+        if let Some(first) = x.first() {
             Ok(first.clone())
         } else {
             Ok(Null)
         }
+    } else if let Str(x) = &par[0] {
+        Ok(x.chars().next().map(Char).unwrap_or(Null))
     } else {
         unimplemented!()
     }
@@ -285,48 +559,796 @@ fn tail(_state: &mut State, par: Tokens) -> Result<Token, Report> {
             x.remove(0);
             Ok(List(x.clone()))
         }
+        Str(x) => Ok(Str(x.chars().skip(1).collect())),
+        _ => unimplemented!(),
+    }
+}
+
+fn index_of(index: f64, len: usize) -> Option<usize> {
+    if index >= 0.0 && index.fract() == 0.0 && (index as usize) < len {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+fn parameter_error(par: &Tokens, position: usize, message: &str, label: String) -> Report {
+    let literal = par
+        .iter()
+        .cloned()
+        .rev()
+        .collect::<Vec<_>>()
+        .literal_enumerate();
+
+    Report::build(ReportKind::Error)
+        .with_message(message)
+        .with_label(
+            Label::new(literal.1[par.len() - position - 1].clone())
+                .with_message(label)
+                .with_color(Color::Red),
+        )
+        .with_source(Source::from(literal.0))
+        .finish()
+}
+
+fn out_of_bounds(par: &Tokens, position: usize, len: usize) -> Report {
+    parameter_error(
+        par,
+        position,
+        "Index Out Of Bounds",
+        format!(
+            "This index is outside of a collection with {} element(s).",
+            len
+        ),
+    )
+}
+
+fn contents(collection: &Token) -> Tokens {
+    match collection {
+        Group(x) | List(x) | Iterator(x) | Set(x) => x.to_vec(),
+        Str(x) => x.chars().map(Char).collect(),
+        Map(x) => x
+            .iter()
+            .map(|(key, value)| List(vec![key.clone(), value.clone()]))
+            .collect(),
+        _ => unimplemented!(),
+    }
+}
+
+fn rebuild(collection: &Token, contents: Tokens) -> Token {
+    match collection {
+        Group(_) => Group(contents),
+        List(_) => List(contents),
+        Iterator(_) => Iterator(contents),
+        Str(_) if contents.iter().all(|x| matches!(x, Char(_))) => {
+            Str(contents.iter().map(text).collect())
+        }
+        Str(_) => Group(contents),
         _ => unimplemented!(),
     }
 }
 
+// Reading outside of a collection gives Null, like head does on an empty
+// collection, whereas modifying outside of it is reported as an error.
+
+fn slice(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let (range, x) = if let [Range(range), x] = par.as_slice() {
+        (range, contents(x))
+    } else {
+        unimplemented!()
+    };
+
+    Ok(match range.bounds(x.len()) {
+        Some(bounds) => rebuild(&par[1], x[bounds].to_vec()),
+        None => Null,
+    })
+}
+
+fn nth(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let (index, x) = if let [Value(index), x] = par.as_slice() {
+        (index, contents(x))
+    } else {
+        unimplemented!()
+    };
+
+    Ok(match index_of(*index, x.len()) {
+        Some(index) => x[index].clone(),
+        None => Null,
+    })
+}
+
+fn set_nth(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let (value, index, mut x) = if let [value, Value(index), x] = par.as_slice() {
+        (value, index, contents(x))
+    } else {
+        unimplemented!()
+    };
+
+    match index_of(*index, x.len()) {
+        Some(index) => {
+            x[index] = value.clone();
+
+            Ok(rebuild(&par[2], x))
+        }
+        None => Err(out_of_bounds(&par, 1, x.len())),
+    }
+}
+
+fn insert_at(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let (value, index, mut x) = if let [value, Value(index), x] = par.as_slice() {
+        (value, index, contents(x))
+    } else {
+        unimplemented!()
+    };
+
+    match index_of(*index, x.len() + 1) {
+        Some(index) => {
+            x.insert(index, value.clone());
+
+            Ok(rebuild(&par[2], x))
+        }
+        None => Err(out_of_bounds(&par, 1, x.len())),
+    }
+}
+
+fn remove_at(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let (index, mut x) = if let [Value(index), x] = par.as_slice() {
+        (index, contents(x))
+    } else {
+        unimplemented!()
+    };
+
+    match index_of(*index, x.len()) {
+        Some(index) => {
+            x.remove(index);
+
+            Ok(rebuild(&par[1], x))
+        }
+        None => Err(out_of_bounds(&par, 0, x.len())),
+    }
+}
+
+fn text(token: &Token) -> String {
+    match token {
+        Char(x) => x.to_string(),
+        _ => convert(token),
+    }
+}
+
+fn concat(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_string(&(text(&par[1]) + &text(&par[0]))))
+}
+
+fn split(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(List(
+        text(&par[1])
+            .split(&text(&par[0]))
+            .map(encode_string)
+            .collect(),
+    ))
+}
+
+fn join(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let x = contents(&par[1]);
+
+    Ok(encode_string(
+        &x.iter().map(text).collect::<Vec<_>>().join(&text(&par[0])),
+    ))
+}
+
+fn trim(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_string(text(&par[0]).trim()))
+}
+
+fn upper(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_string(&text(&par[0]).to_uppercase()))
+}
+
+fn lower(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_string(&text(&par[0]).to_lowercase()))
+}
+
+fn contains(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let found = match &par[1] {
+        Set(x) => x
+            .binary_search_by(|element| compare(element, &par[0]))
+            .is_ok(),
+        Group(x) | List(x) if par[1].is_string().is_none() => x
+            .iter()
+            .any(|element| compare(element, &par[0]) == Ordering::Equal),
+        _ => text(&par[1]).contains(&text(&par[0])),
+    };
+
+    Ok(Value(found as i32 as f64))
+}
+
+fn starts_with(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Value(
+        text(&par[1]).starts_with(&text(&par[0])) as i32 as f64
+    ))
+}
+
+fn ends_with(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Value(text(&par[1]).ends_with(&text(&par[0])) as i32 as f64))
+}
+
+fn replace(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_string(
+        &text(&par[2]).replace(&text(&par[1]), &text(&par[0])),
+    ))
+}
+
+fn find(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let haystack = text(&par[1]);
+
+    Ok(match haystack.find(&text(&par[0])) {
+        Some(index) => Value(haystack[..index].chars().count() as f64),
+        None => Null,
+    })
+}
+
+fn chars(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(Iterator(text(&par[0]).chars().map(Char).collect()))
+}
+
+fn to_number(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match text(&par[0]).trim().parse::<f64>() {
+        Ok(x) => Value(x),
+        Err(_) => Null,
+    })
+}
+
+fn to_string(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_string(&convert(&par[0])))
+}
+
+fn format(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let x = contents(&par[0]);
+
+    let mut values = x.iter();
+    let mut pieces = text(&par[1])
+        .split("{}")
+        .map(str::to_string)
+        .collect::<Vec<_>>()
+        .into_iter();
+    let mut result = pieces.next().unwrap_or_default();
+
+    for piece in pieces {
+        match values.next() {
+            Some(value) => result.push_str(&text(value)),
+            None => result.push_str("{}"),
+        }
+
+        result.push_str(&piece);
+    }
+
+    Ok(encode_string(&result))
+}
+
+fn ordering(par: &Tokens, result: Tokens) -> Result<Ordering, Report> {
+    match result.as_slice() {
+        [Value(x)] if *x < 0.0 => Ok(Ordering::Less),
+        [Value(x)] if *x > 0.0 => Ok(Ordering::Greater),
+        [Value(_)] => Ok(Ordering::Equal),
+        _ => Err(parameter_error(
+            par,
+            0,
+            "Invalid Comparator",
+            format!(
+                "This produced {} instead of a single Value.",
+                result.literal()
+            ),
+        )),
+    }
+}
+
+fn sort(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let mut x = contents(&par[0]);
+    x.sort_by(compare);
+
+    Ok(rebuild(&par[0], x))
+}
+
+/// Sorts stably by merging, so that a comparator that fails stops the sort
+/// there and then, and one that is not consistent can not make it panic.
+fn merge_sort(
+    mut x: Tokens,
+    compare: &mut impl FnMut(&Token, &Token) -> Result<Ordering, Report>,
+) -> Result<Tokens, Report> {
+    if x.len() <= 1 {
+        return Ok(x);
+    }
+
+    let right = x.split_off(x.len() / 2);
+    let mut left = merge_sort(x, compare)?.into_iter().peekable();
+    let mut right = merge_sort(right, compare)?.into_iter().peekable();
+    let mut merged = vec![];
+
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Taking from the left unless the right is strictly less keeps
+        // equal elements in the order they came in.
+        merged.push(match compare(b, a)? {
+            Ordering::Less => right.next().unwrap(),
+            _ => left.next().unwrap(),
+        });
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+
+    Ok(merged)
+}
+
+fn sort_by(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let func = &call(_state, vec![par[0].clone()])?;
+
+    let x = merge_sort(contents(&par[1]), &mut |a, b| {
+        _state
+            .eval(vec![a.clone(), b.clone(), func.clone()])
+            .and_then(|result| ordering(&par, result))
+    })?;
+
+    Ok(rebuild(&par[1], x))
+}
+
+fn keys_of(_state: &mut State, par: &Tokens, x: &Tokens) -> Result<Tokens, Report> {
+    let func = &call(_state, vec![par[0].clone()])?;
+    let mut keys = vec![];
+
+    for element in x {
+        match _state.eval(vec![element.clone(), func.clone()])?.as_slice() {
+            [key] => keys.push(key.clone()),
+            result => {
+                return Err(parameter_error(
+                    par,
+                    0,
+                    "Invalid Key",
+                    format!(
+                        "This produced {} instead of a single key.",
+                        result.to_vec().literal()
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
+fn sort_by_key(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let x = contents(&par[1]);
+    let keys = keys_of(_state, &par, &x)?;
+
+    let mut pairs = keys.into_iter().zip(x).collect::<Vec<_>>();
+    pairs.sort_by(|a, b| compare(&a.0, &b.0));
+
+    Ok(rebuild(
+        &par[1],
+        pairs.into_iter().map(|(_, element)| element).collect(),
+    ))
+}
+
+fn binary_search(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(
+        match contents(&par[1]).binary_search_by(|element| compare(element, &par[0])) {
+            Ok(index) => Value(index as f64),
+            Err(_) => Null,
+        },
+    )
+}
+
+fn group_by(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let x = contents(&par[1]);
+    let keys = keys_of(_state, &par, &x)?;
+
+    let mut groups: Vec<(Token, Tokens)> = vec![];
+
+    for (key, element) in keys.into_iter().zip(x) {
+        match groups.last_mut() {
+            Some((last, group)) if compare(last, &key) == Ordering::Equal => group.push(element),
+            _ => groups.push((key, vec![element])),
+        }
+    }
+
+    Ok(List(
+        groups
+            .into_iter()
+            .map(|(_, group)| rebuild(&par[1], group))
+            .collect(),
+    ))
+}
+
+fn partition(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let func = &call(_state, vec![par[0].clone()])?;
+    let (mut matching, mut rest) = (vec![], vec![]);
+
+    for element in contents(&par[1]) {
+        if _state.eval(vec![element.clone(), func.clone()])? == vec![Value(1.0)] {
+            matching.push(element);
+        } else {
+            rest.push(element);
+        }
+    }
+
+    Ok(List(vec![
+        rebuild(&par[1], matching),
+        rebuild(&par[1], rest),
+    ]))
+}
+
+fn pairs(map: &Token) -> &Vec<(Token, Token)> {
+    if let Map(x) = map {
+        x
+    } else {
+        unimplemented!()
+    }
+}
+
+fn get(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let x = pairs(&par[1]);
+
+    Ok(match x.binary_search_by(|(key, _)| compare(key, &par[0])) {
+        Ok(index) => x[index].1.clone(),
+        Err(_) => Null,
+    })
+}
+
+fn put(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let mut x = pairs(&par[2]).to_vec();
+    insert_pair(&mut x, par[1].clone(), par[0].clone());
+
+    Ok(Map(x))
+}
+
+fn remove(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    if let Set(x) = &par[1] {
+        return Ok(Set(x
+            .iter()
+            .filter(|element| compare(element, &par[0]) != Ordering::Equal)
+            .cloned()
+            .collect()));
+    }
+
+    let mut x = pairs(&par[1]).to_vec();
+
+    if let Ok(index) = x.binary_search_by(|(key, _)| compare(key, &par[0])) {
+        x.remove(index);
+    }
+
+    Ok(Map(x))
+}
+
+fn keys(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(List(
+        pairs(&par[0]).iter().map(|(key, _)| key.clone()).collect(),
+    ))
+}
+
+fn values(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(List(
+        pairs(&par[0])
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect(),
+    ))
+}
+
+fn has_key(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(
+        if pairs(&par[1])
+            .binary_search_by(|(key, _)| compare(key, &par[0]))
+            .is_ok()
+        {
+            Value(1.0)
+        } else {
+            Value(0.0)
+        },
+    )
+}
+
+fn merge(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let mut x = pairs(&par[1]).to_vec();
+
+    for (key, value) in pairs(&par[0]) {
+        insert_pair(&mut x, key.clone(), value.clone());
+    }
+
+    Ok(Map(x))
+}
+
+fn collect_map(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let arr = if let Iterator(x) = &par[0] {
+        x.to_vec()
+    } else {
+        unimplemented!()
+    };
+
+    let mut x = vec![];
+
+    for (index, pair) in arr.iter().enumerate() {
+        match pair {
+            List(pair) | Group(pair) if pair.len() == 2 && has_type(&pair[0], "Key") => {
+                insert_pair(&mut x, pair[0].clone(), pair[1].clone())
+            }
+            List(pair) | Group(pair) if pair.len() == 2 => {
+                return Err(parameter_error(
+                    &arr.iter().cloned().rev().collect(),
+                    index,
+                    "Invalid Key",
+                    format!(
+                        "This is keyed by a {} rather than an atom, string or number.",
+                        type_of(&pair[0])
+                    ),
+                ))
+            }
+            _ => {
+                return Err(parameter_error(
+                    &arr.iter().cloned().rev().collect(),
+                    index,
+                    "Invalid Pair",
+                    format!("This is {} rather than a key and a value.", convert(pair)),
+                ))
+            }
+        }
+    }
+
+    Ok(Map(x))
+}
+
+fn members(set: &Token) -> &Vec<Token> {
+    if let Set(x) = set {
+        x
+    } else {
+        unimplemented!()
+    }
+}
+
+fn union(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_set(
+        [members(&par[1]).to_vec(), members(&par[0]).to_vec()].concat(),
+    ))
+}
+
+fn intersection(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let other = members(&par[0]);
+
+    Ok(Set(members(&par[1])
+        .iter()
+        .filter(|element| other.binary_search_by(|x| compare(x, element)).is_ok())
+        .cloned()
+        .collect()))
+}
+
+fn difference(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let other = members(&par[0]);
+
+    Ok(Set(members(&par[1])
+        .iter()
+        .filter(|element| other.binary_search_by(|x| compare(x, element)).is_err())
+        .cloned()
+        .collect()))
+}
+
+fn insert(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let mut x = members(&par[1]).to_vec();
+
+    if let Err(index) = x.binary_search_by(|element| compare(element, &par[0])) {
+        x.insert(index, par[0].clone());
+    }
+
+    Ok(Set(x))
+}
+
+fn to_set(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(encode_set(contents(&par[0])))
+}
+
+fn field(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    let (name, fields) = if let [Atom(name), Record(_, fields)] = par.as_slice() {
+        (name, fields)
+    } else {
+        unimplemented!()
+    };
+
+    match fields.iter().find(|(field, _)| field == name) {
+        Some((_, value)) => Ok(value.clone()),
+        None => Err(parameter_error(
+            &par,
+            0,
+            "Unknown Field",
+            format!("{} has no field called {}.", type_of(&par[1]), name),
+        )),
+    }
+}
+
+fn is_variant(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match par.as_slice() {
+        [Atom(x), Variant(_, tag, _)] if x == tag => Value(1.0),
+        _ => Value(0.0),
+    })
+}
+
+fn payload(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match par.as_slice() {
+        [Value(index), Variant(_, _, payload)] => match index_of(*index, payload.len()) {
+            Some(index) => payload[index].clone(),
+            None => Null,
+        },
+        _ => Null,
+    })
+}
+
+// The pattern primitives below never fail on a value of the wrong shape, as
+// every predicate of a pattern guard is evaluated before they are combined.
+
+fn parts(token: &Token) -> Option<Tokens> {
+    match token {
+        Group(_) | List(_) | Iterator(_) | Str(_) => Some(contents(token)),
+        Record(_, fields) => Some(fields.iter().map(|(_, x)| x.clone()).collect()),
+        Variant(_, _, payload) => Some(payload.to_vec()),
+        _ => None,
+    }
+}
+
+fn has_length(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match (&par[0], parts(&par[1])) {
+        (Value(n), Some(x)) if x.len() as f64 == *n => Value(1.0),
+        _ => Value(0.0),
+    })
+}
+
+fn has_min_length(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match (&par[0], parts(&par[1])) {
+        (Value(n), Some(x)) if x.len() as f64 >= *n => Value(1.0),
+        _ => Value(0.0),
+    })
+}
+
+fn element(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match (&par[0], parts(&par[1])) {
+        (Value(index), Some(x)) => match index_of(*index, x.len()) {
+            Some(index) => x[index].clone(),
+            None => Null,
+        },
+        _ => Null,
+    })
+}
+
+fn elements_from(_state: &mut State, par: Tokens) -> Result<Token, Report> {
+    Ok(match (&par[0], &par[1]) {
+        (Value(index), x @ (Group(_) | List(_) | Iterator(_) | Str(_))) => {
+            let x_contents = contents(x);
+            let start = (*index as usize).min(x_contents.len());
+
+            rebuild(x, x_contents[start..].to_vec())
+        }
+        _ => Null,
+    })
+}
+
 pub static COMPLEX_TYPES: phf::Map<&'static str, &[&'static str]> = phf_map! {
     "Literal" => &["Lambda", "FunctionLiteral", "ContainerLiteral"],
-    "Iterable" => &["Group", "List"],
+    "Iterable" => &["Group", "List", "String"],
+    "Text" => &["String", "Char"],
+    "Sequence" => &["Group", "List", "String", "Iterator"],
+    "Enumerable" => &["Group", "List", "String", "Map", "Set"],
+    "Searchable" => &["Group", "List", "String", "Set"],
+    "Keyed" => &["Map", "Set"],
+    "Key" => &["Atom", "String", "Value"],
 };
 
-pub static FUNCTIONS: phf::Map<&'static str, (FunctionRef, &[&'static str])> = phf_map! {
-    "type" => (type_of_container, &["Any"]),
-    "sum" => (sum, &["Iterable"]),
-    "add" => (add, &["Value", "Value"]),
-    "sub" => (sub, &["Value", "Value"]),
-    "mul" => (mul, &["Value", "Value"]),
-    "div" => (div, &["Value", "Value"]),
-    "mod" => (modulo, &["Value", "Value"]),
-    "+" => (add, &["Value", "Value"]),
-    "-" => (sub, &["Value", "Value"]),
-    "*" => (mul, &["Value", "Value"]),
-    "/" => (div, &["Value", "Value"]),
-    "%" => (modulo, &["Value", "Value"]),
-    "greater" => (greater, &["Value", "Value"]),
-    "less" => (less, &["Value", "Value"]),
-    "or" => (or, &["Value", "Value"]),
-    "and" => (and, &["Value", "Value"]),
-    "not" => (not, &["Value"]),
-    "len" => (len, &["Iterable"]),
-    "reverse" => (reverse, &["Iterable"]),
-    "OUTPUT" => (output, &["Any"]),
-    "=" => (eq, &["Any", "Any"]),
-    "eq" => (eq, &["Any", "Any"]),
-    "axe" => (axe, &["Any"]),
-    "swap" => (|_, par| Ok(par[0].clone()), &["Any", "Any"]),
-    "call" => (call, &["Literal"]),
-    "map" => (map, &["Literal", "Iterator"]),
-    "foldr" => (foldr, &["Any", "Literal", "Iterator"]),
-    "foldl" => (foldl, &["Any", "Literal", "Iterator"]),
-    "iter" => (iter, &["Iterable"]),
-    "collect_group" => (collect_group, &["Iterator"]),
-    "collect_list" => (collect_list, &["Iterator"]),
-    "lambda" => (lambda, &["List"]),
-    "head" => (head, &["Iterable"]),
-    "tail" => (tail, &["Iterable"])
+/// Each builtin with the types it takes from the top of the stack, top first,
+/// and the types it leaves there, deepest first, the latter used to check
+/// code before it runs. A result of `*` means the builtin runs code whose
+/// effect is not known.
+pub static FUNCTIONS: phf::Map<&'static str, (FunctionRef, &[&'static str], &[&'static str])> = phf_map! {
+    "type" => (Single(type_of_container), &["Any"], &["String"]),
+    "sum" => (Single(sum), &["Iterable"], &["Value"]),
+    "add" => (Single(add), &["Value", "Value"], &["Value"]),
+    "sub" => (Single(sub), &["Value", "Value"], &["Value"]),
+    "mul" => (Single(mul), &["Value", "Value"], &["Value"]),
+    "div" => (Single(div), &["Value", "Value"], &["Value"]),
+    "mod" => (Single(modulo), &["Value", "Value"], &["Value"]),
+    "+" => (Single(add), &["Value", "Value"], &["Value"]),
+    "-" => (Single(sub), &["Value", "Value"], &["Value"]),
+    "*" => (Single(mul), &["Value", "Value"], &["Value"]),
+    "/" => (Single(div), &["Value", "Value"], &["Value"]),
+    "%" => (Single(modulo), &["Value", "Value"], &["Value"]),
+    "greater" => (Single(greater), &["Any", "Any"], &["Value"]),
+    "less" => (Single(less), &["Any", "Any"], &["Value"]),
+    ">" => (Single(greater_than), &["Any", "Any"], &["Value"]),
+    "<" => (Single(less_than), &["Any", "Any"], &["Value"]),
+    ">=" => (Single(greater_eq), &["Any", "Any"], &["Value"]),
+    "<=" => (Single(less_eq), &["Any", "Any"], &["Value"]),
+    "compare" => (Single(compare_container), &["Any", "Any"], &["Value"]),
+    "or" => (Single(or), &["Value", "Value"], &["Value"]),
+    "and" => (Single(and), &["Value", "Value"], &["Value"]),
+    "not" => (Single(not), &["Value"], &["Value"]),
+    "len" => (Single(len), &["Enumerable"], &["Value"]),
+    "reverse" => (Single(reverse), &["Iterable"], &["Any"]),
+    "OUTPUT" => (Single(output), &["Any"], &[]),
+    "=" => (Single(eq), &["Any", "Any"], &["Value"]),
+    "eq" => (Single(eq), &["Any", "Any"], &["Value"]),
+    "neq" => (Single(neq), &["Any", "Any"], &["Value"]),
+    "!=" => (Single(neq), &["Any", "Any"], &["Value"]),
+    "axe" => (Single(axe), &["Any"], &[]),
+    "swap" => (Many(|_, par| Ok(par)), &["Any", "Any"], &["Any", "Any"]),
+    "dup" => (Many(|_, par| Ok(vec![par[0].clone(), par[0].clone()])), &["Any"], &["Any", "Any"]),
+    "drop" => (Many(|_, _| Ok(vec![])), &["Any"], &[]),
+    "over" => (Many(over), &["Any", "Any"], &["Any", "Any", "Any"]),
+    "rot" => (Many(rot), &["Any", "Any", "Any"], &["Any", "Any", "Any"]),
+    "-rot" => (Many(unrot), &["Any", "Any", "Any"], &["Any", "Any", "Any"]),
+    "nip" => (Many(|_, par| Ok(vec![par[0].clone()])), &["Any", "Any"], &["Any"]),
+    "tuck" => (Many(tuck), &["Any", "Any"], &["Any", "Any", "Any"]),
+    "2dup" => (Many(two_dup), &["Any", "Any"], &["Any", "Any", "Any", "Any"]),
+    "pick" => (Many(pick), &["Value", "..."], &["*"]),
+    "roll" => (Many(roll), &["Value", "..."], &["*"]),
+    "depth" => (Many(depth), &["..."], &["*"]),
+    "clear" => (Many(|_, _| Ok(vec![])), &["..."], &["*"]),
+    "call" => (Single(call), &["Literal"], &["*"]),
+    "curry" => (Single(curry), &["Literal", "Any"], &["Lambda"]),
+    "partial" => (Single(partial), &["Value", "Literal"], &["*"]),
+    "compose" => (Single(compose), &["Literal", "Literal"], &["Lambda"]),
+    "flip" => (Single(flip), &["Literal"], &["Lambda"]),
+    "identity" => (Single(|_, par| Ok(par[0].clone())), &["Any"], &["Any"]),
+    "const" => (Single(constant), &["Any"], &["Lambda"]),
+    "map" => (Single(map), &["Literal", "Iterator"], &["Iterator"]),
+    "par_map" => (Single(par_map), &["Literal", "Iterator"], &["Iterator"]),
+    "par_filter" => (Single(par_filter), &["Literal", "Iterator"], &["Iterator"]),
+    "foldr" => (Single(foldr), &["Any", "Literal", "Iterator"], &["Any"]),
+    "foldl" => (Single(foldl), &["Any", "Literal", "Iterator"], &["Any"]),
+    "iter" => (Single(iter), &["Enumerable"], &["Iterator"]),
+    "collect_group" => (Single(collect_group), &["Iterator"], &["Group"]),
+    "collect_list" => (Single(collect_list), &["Iterator"], &["List"]),
+    "collect_string" => (Single(collect_string), &["Iterator"], &["String"]),
+    "lambda" => (Single(lambda), &["List"], &["Lambda"]),
+    "head" => (Single(head), &["Iterable"], &["Any"]),
+    "tail" => (Single(tail), &["Iterable"], &["Any"]),
+    "slice" => (Single(slice), &["Range", "Iterable"], &["Any"]),
+    "nth" => (Single(nth), &["Value", "Iterable"], &["Any"]),
+    "set_nth" => (Single(set_nth), &["Any", "Value", "Iterable"], &["Any"]),
+    "insert_at" => (Single(insert_at), &["Any", "Value", "Iterable"], &["Any"]),
+    "remove_at" => (Single(remove_at), &["Value", "Iterable"], &["Any"]),
+    "concat" => (Single(concat), &["Text", "Text"], &["String"]),
+    "split" => (Single(split), &["Text", "String"], &["List"]),
+    "join" => (Single(join), &["Text", "Iterable"], &["String"]),
+    "trim" => (Single(trim), &["String"], &["String"]),
+    "upper" => (Single(upper), &["String"], &["String"]),
+    "lower" => (Single(lower), &["String"], &["String"]),
+    "contains" => (Single(contains), &["Any", "Searchable"], &["Value"]),
+    "starts_with" => (Single(starts_with), &["Text", "String"], &["Value"]),
+    "ends_with" => (Single(ends_with), &["Text", "String"], &["Value"]),
+    "replace" => (Single(replace), &["Text", "Text", "String"], &["String"]),
+    "find" => (Single(find), &["Text", "String"], &["Any"]),
+    "chars" => (Single(chars), &["String"], &["Iterator"]),
+    "to_number" => (Single(to_number), &["String"], &["Any"]),
+    "to_string" => (Single(to_string), &["Any"], &["String"]),
+    "format" => (Single(format), &["Iterable", "String"], &["String"]),
+    "sort" => (Single(sort), &["Sequence"], &["Any"]),
+    "sort_by" => (Single(sort_by), &["Literal", "Sequence"], &["Any"]),
+    "sort_by_key" => (Single(sort_by_key), &["Literal", "Sequence"], &["Any"]),
+    "binary_search" => (Single(binary_search), &["Any", "Sequence"], &["Any"]),
+    "group_by" => (Single(group_by), &["Literal", "Sequence"], &["List"]),
+    "partition" => (Single(partition), &["Literal", "Sequence"], &["List"]),
+    "get" => (Single(get), &["Key", "Map"], &["Any"]),
+    "put" => (Single(put), &["Any", "Key", "Map"], &["Map"]),
+    "remove" => (Single(remove), &["Any", "Keyed"], &["Any"]),
+    "keys" => (Single(keys), &["Map"], &["List"]),
+    "values" => (Single(values), &["Map"], &["List"]),
+    "has_key" => (Single(has_key), &["Key", "Map"], &["Value"]),
+    "merge" => (Single(merge), &["Map", "Map"], &["Map"]),
+    "collect_map" => (Single(collect_map), &["Iterator"], &["Map"]),
+    "union" => (Single(union), &["Set", "Set"], &["Set"]),
+    "intersection" => (Single(intersection), &["Set", "Set"], &["Set"]),
+    "difference" => (Single(difference), &["Set", "Set"], &["Set"]),
+    "insert" => (Single(insert), &["Any", "Set"], &["Set"]),
+    "to_set" => (Single(to_set), &["Sequence"], &["Set"]),
+    "collect_set" => (Single(to_set), &["Iterator"], &["Set"]),
+    "field" => (Single(field), &["Atom", "Record"], &["Any"]),
+    "is_variant" => (Single(is_variant), &["Atom", "Any"], &["Value"]),
+    "payload" => (Single(payload), &["Value", "Any"], &["Any"]),
+    "has_length" => (Single(has_length), &["Value", "Any"], &["Value"]),
+    "has_min_length" => (Single(has_min_length), &["Value", "Any"], &["Value"]),
+    "element" => (Single(element), &["Value", "Any"], &["Any"]),
+    "elements_from" => (Single(elements_from), &["Value", "Any"], &["Any"])
 };
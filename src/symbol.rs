@@ -0,0 +1,101 @@
+use crate::stdlib::FUNCTIONS;
+use crate::FunctionRef;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+type Builtin = (FunctionRef, &'static [&'static str]);
+
+// Names are stored in segments which double in size, the nth holding 2^n of
+// them. Segments are never moved or freed, so a name can be borrowed for as
+// long as the program runs, and read without a lock once it is stored.
+const SEGMENTS: usize = 32;
+
+type Segment = Box<[OnceLock<Box<str>>]>;
+
+static NAMES: [OnceLock<Segment>; SEGMENTS] = [const { OnceLock::new() }; SEGMENTS];
+
+/// The segment and the place in it where the name of a symbol is stored.
+fn locate(index: u32) -> (usize, usize) {
+    let position = index as usize + 1;
+    let segment = position.ilog2() as usize;
+
+    (segment, position - (1 << segment))
+}
+
+/// Stores the name of a new symbol. Only called with the interner's lock
+/// held, which keeps two names from being stored in the same place.
+fn store(index: u32, name: &str) -> &'static str {
+    let (segment, offset) = locate(index);
+    let segment =
+        NAMES[segment].get_or_init(|| (0..1 << segment).map(|_| OnceLock::new()).collect());
+
+    segment[offset].get_or_init(|| name.into())
+}
+
+lazy_static! {
+    static ref BUILTINS: Vec<Builtin> = FUNCTIONS
+        .entries()
+        .map(|(_, (function, parameters, _))| (*function, *parameters))
+        .collect();
+    static ref INTERNER: RwLock<HashMap<&'static str, Symbol>> = RwLock::new(
+        FUNCTIONS
+            .entries()
+            .enumerate()
+            .map(|(index, (name, _))| (store(index as u32, name), Symbol(index as u32)))
+            .collect()
+    );
+}
+
+/// An interned container or function name, which is compared and hashed as
+/// a number. Builtins are interned first, in the order of FUNCTIONS, so that
+/// a builtin's symbol is also where it is found in BUILTINS.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        if let Some(symbol) = INTERNER.read().unwrap().get(name) {
+            return *symbol;
+        }
+
+        let mut interner = INTERNER.write().unwrap();
+
+        // Another thread may have interned it between the two locks.
+        if let Some(symbol) = interner.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(interner.len() as u32);
+
+        interner.insert(store(symbol.0, name), symbol);
+
+        symbol
+    }
+
+    /// The name of the symbol, which was stored before the symbol was made,
+    /// so it is found without taking the interner's lock.
+    pub fn as_str(self) -> &'static str {
+        let (segment, offset) = locate(self.0);
+
+        NAMES[segment].get().unwrap()[offset].get().unwrap()
+    }
+
+    /// The function and parameter types of the builtin with this name.
+    pub(crate) fn builtin(self) -> Option<&'static Builtin> {
+        BUILTINS.get(self.0 as usize)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
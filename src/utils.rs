@@ -1,25 +1,64 @@
 use charon_ariadne::{Color, Label, Report, ReportBuilder, ReportKind, Source};
-use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::slice;
 
-use crate::stdlib::{COMPLEX_TYPES, FUNCTIONS};
-use crate::{State, Token, Tokens};
+use crate::stdlib::COMPLEX_TYPES;
+use crate::{Container, Range, State, Token, Tokens};
 
 pub fn convert(token: &Token) -> String {
     match token {
         Token::Value(x) => x.to_string(),
+        Token::Str(x) => x.to_string(),
         Token::Atom(x) => format!(":{}", x),
-        Token::Special(x) | Token::Container(x) | Token::Function(x) => x.to_string(),
+        Token::Special(x) => x.to_string(),
+        Token::Container(x) | Token::Function(x) => x.to_string(),
         Token::Group(contents) => match token.is_string() {
             Some(x) => x,
             _ => format!("{{{}}}", contents.literal()),
         },
         Token::List(contents) => format!("[{}]", contents.literal()),
+        Token::Lambda(contents) => format!("\\({})", contents.literal()),
+        Token::Set(contents) => format!("#{{{}}}", contents.literal()),
+        Token::Record(name, fields) => format!(
+            "{}({})",
+            name,
+            fields
+                .iter()
+                .map(|(field, value)| format!("{}: {}", field, convert(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Token::Variant(_, tag, payload) if payload.is_empty() => format!(":{}", tag),
+        Token::Variant(_, tag, payload) => format!(":{}({})", tag, payload.literal()),
+        Token::Map(pairs) => format!(
+            "%{{{}}}",
+            pairs
+                .iter()
+                .flat_map(|(key, value)| [key.clone(), value.clone()])
+                .collect::<Vec<_>>()
+                .literal()
+        ),
         Token::FunctionLiteral(x) | Token::ContainerLiteral(x) => format!("`{}`", x),
+        Token::Parameter(x) => format!("${}", x),
+        Token::Capture(x) => format!("^{}", x),
+        Token::Range(Range::Full(x)) => format!("#{}..{}", x.start(), x.end()),
+        Token::Range(Range::To(x)) => format!("#{}..", x.start),
+        Token::Range(Range::From(x)) => format!("#..{}", x.end - 1),
         _ => format!("{:?}", token),
     }
 }
 
 pub fn type_of(token: &Token) -> String {
+    // An empty group has no characters to make it a string, so it keeps the
+    // type it had before strings were told apart.
+    let empty = matches!(token, Token::Group(x) if x.is_empty());
+
+    if token.is_string().is_some() && !empty {
+        return "String".to_string();
+    } else if let Token::Record(name, _) | Token::Variant(name, _, _) = token {
+        return name.to_string();
+    }
+
     let mut buffer = String::new();
 
     for character in token.to_string().chars() {
@@ -33,12 +72,231 @@ pub fn type_of(token: &Token) -> String {
     buffer
 }
 
+/// The number of parameters a body refers to, found from the highest `$n`
+/// or `^n` within it, which is a container's arity when it has no signature.
+/// A `$n` inside a lambda belongs to the lambda itself, so only its captures
+/// are counted.
+pub(crate) fn highest_rec(tokens: &[Token]) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Group(inner) | Token::List(inner) => highest_rec(inner),
+            Token::Lambda(inner) => highest_capture(inner),
+            Token::Record(_, fields) => fields
+                .iter()
+                .map(|(_, value)| highest_rec(slice::from_ref(value)))
+                .max()
+                .unwrap_or(0),
+            Token::Variant(_, _, payload) => highest_rec(payload),
+            Token::Parameter(index) | Token::Capture(index) => index + 1,
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn highest_capture(tokens: &[Token]) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Capture(index) => index + 1,
+            Token::Group(inner) | Token::List(inner) | Token::Lambda(inner) => {
+                highest_capture(inner)
+            }
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Whether a collection takes any values from the body it is made in, at
+/// however deep a level.
+pub(crate) fn captures(token: &Token) -> bool {
+    match token {
+        Token::Group(inner) | Token::List(inner) | Token::Lambda(inner) => {
+            highest_capture(inner) > 0
+        }
+        _ => false,
+    }
+}
+
+/// The types a container takes from the top of the stack, top first, which
+/// are those of its signature or otherwise as many Any as it has parameters.
+pub(crate) fn parameter_types(container: &Container) -> Vec<&str> {
+    match &container.signature {
+        Some(signature) => signature
+            .parameters
+            .iter()
+            .rev()
+            .map(String::as_str)
+            .collect(),
+        None => {
+            let highest = container
+                .cases
+                .iter()
+                .map(|(x, y)| highest_rec(x).max(highest_rec(y)))
+                .max()
+                .unwrap_or(0);
+
+            vec!["Any"; highest]
+        }
+    }
+}
+
+/// Reports a call to a container that is not defined, as can happen when its
+/// name is only made while running, showing the call on top of the stack.
+pub(crate) fn unknown_container(stack: &Tokens, func: &Token) -> Report {
+    let literal = [stack.clone(), vec![func.clone()]]
+        .concat()
+        .literal_enumerate();
+
+    Report::build(ReportKind::Error)
+        .with_message("Unknown Container")
+        .with_label(
+            Label::new(literal.1[literal.1.len() - 1].clone())
+                .with_message("No container of this name is defined.".to_string())
+                .with_color(Color::Red),
+        )
+        .with_source(Source::from(literal.0))
+        .finish()
+}
+
+/// Whether a token satisfies a type from a signature, which may be Any, the
+/// name of a type or record, or one of the groups in COMPLEX_TYPES.
+pub(crate) fn has_type(token: &Token, expected: &str) -> bool {
+    expected == "Any"
+        || type_of(token) == expected
+        || (expected == "Record" && matches!(token, Token::Record(..)))
+        || COMPLEX_TYPES
+            .get(expected)
+            .is_some_and(|group| group.contains(&type_of(token).as_str()))
+}
+
+static TYPE_ORDER: &[&str] = &[
+    "Null", "Value", "Char", "String", "Atom", "Group", "List", "Iterator", "Map", "Set", "Range",
+];
+
+fn rank(token: &Token) -> (usize, String) {
+    let name = type_of(token);
+
+    (
+        TYPE_ORDER
+            .iter()
+            .position(|x| *x == name)
+            .unwrap_or(TYPE_ORDER.len()),
+        name,
+    )
+}
+
+/// A value as `compare` orders it.
+fn ordered(x: f64) -> f64 {
+    if x.is_nan() {
+        f64::NAN
+    } else {
+        x + 0.0
+    }
+}
+
+fn compare_all(x: &[Token], y: &[Token]) -> Ordering {
+    x.iter()
+        .zip(y)
+        .map(|(x, y)| compare(x, y))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| x.len().cmp(&y.len()))
+}
+
+/// Whether two tokens are structurally equal. Values are equal as f64s are, so
+/// that 0 equals -0 and, unlike when ordering with `compare`, NaN equals nothing.
+pub fn equal(x: &Token, y: &Token) -> bool {
+    if let (Some(x), Some(y)) = (x.is_string(), y.is_string()) {
+        return x == y;
+    }
+
+    let all =
+        |x: &[Token], y: &[Token]| x.len() == y.len() && x.iter().zip(y).all(|(x, y)| equal(x, y));
+
+    match (x, y) {
+        (Token::Value(x), Token::Value(y)) => x == y,
+        (Token::Group(x), Token::Group(y))
+        | (Token::List(x), Token::List(y))
+        | (Token::Iterator(x), Token::Iterator(y))
+        | (Token::Set(x), Token::Set(y))
+        | (Token::Lambda(x), Token::Lambda(y))
+        | (Token::ActiveLambda(x), Token::ActiveLambda(y)) => all(x, y),
+        (Token::Record(x_name, x), Token::Record(y_name, y)) => {
+            x_name == y_name
+                && x.len() == y.len()
+                && x.iter()
+                    .zip(y)
+                    .all(|(x, y)| x.0 == y.0 && equal(&x.1, &y.1))
+        }
+        (Token::Variant(x_name, x_tag, x), Token::Variant(y_name, y_tag, y)) => {
+            x_name == y_name && x_tag == y_tag && all(x, y)
+        }
+        (Token::Map(x), Token::Map(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .zip(y)
+                    .all(|(x, y)| equal(&x.0, &y.0) && equal(&x.1, &y.1))
+        }
+        _ => compare(x, y) == Ordering::Equal,
+    }
+}
+
+/// Orders any two tokens, first by type and then by their contents so that
+/// strings compare lexicographically and collections element by element.
+/// Values are ordered with -0 taken for 0, as `equal` does, and every NaN
+/// taken for one value above all others, so that a set or map holds one NaN.
+pub fn compare(x: &Token, y: &Token) -> Ordering {
+    if let (Some(x), Some(y)) = (x.is_string(), y.is_string()) {
+        return x.cmp(&y);
+    }
+
+    match (x, y) {
+        (Token::Value(x), Token::Value(y)) => ordered(*x).total_cmp(&ordered(*y)),
+        (Token::Char(x), Token::Char(y)) => x.cmp(y),
+        (Token::Parameter(x), Token::Parameter(y)) | (Token::Capture(x), Token::Capture(y)) => {
+            x.cmp(y)
+        }
+        (Token::Group(x), Token::Group(y))
+        | (Token::List(x), Token::List(y))
+        | (Token::Iterator(x), Token::Iterator(y))
+        | (Token::Set(x), Token::Set(y))
+        | (Token::Lambda(x), Token::Lambda(y))
+        | (Token::ActiveLambda(x), Token::ActiveLambda(y)) => compare_all(x, y),
+        (Token::Record(x_name, x), Token::Record(y_name, y)) if x_name == y_name => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(&x.1, &y.1))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal),
+        (Token::Variant(x_name, x_tag, x), Token::Variant(y_name, y_tag, y))
+            if x_name == y_name =>
+        {
+            x_tag.cmp(y_tag).then_with(|| compare_all(x, y))
+        }
+        (Token::Map(x), Token::Map(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(&x.0, &y.0).then_with(|| compare(&x.1, &y.1)))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        _ => rank(x)
+            .cmp(&rank(y))
+            .then_with(|| convert(x).cmp(&convert(y))),
+    }
+}
+
+#[allow(clippy::result_large_err)]
 pub trait Utils {
-    fn get_par(
+    fn get_par(&mut self, func: &Token, state: &State, base: usize) -> Result<Tokens, Report>;
+    fn take_par<T: AsRef<str>>(&mut self, func: &Token, parameters: &[T])
+        -> Result<Tokens, Report>;
+    fn take_above<T: AsRef<str>>(
         &mut self,
-        func: Token,
-        reference_code: Tokens,
-        state: &State,
+        base: usize,
+        func: &Token,
+        parameters: &[T],
     ) -> Result<Tokens, Report>;
     fn as_nums(&self) -> Vec<f64>;
     fn bundle(&self) -> Tokens;
@@ -49,14 +307,15 @@ pub trait Utils {
 }
 
 impl Token {
-    fn is_string(&self) -> Option<String> {
+    pub(crate) fn is_string(&self) -> Option<String> {
         match self {
+            Token::Str(x) => Some(x.to_string()),
             Token::Group(contents) => {
                 let mut result = String::new();
 
                 for token in contents {
                     match token {
-                        Token::Char(y) => result.push_str(&y.to_string()),
+                        Token::Char(y) => result.push(*y),
                         _ => return None,
                     }
                 }
@@ -68,94 +327,117 @@ impl Token {
     }
 }
 
-impl Utils for Tokens {
-    fn get_par(
-        &mut self,
-        func: Token,
-        reference_code: Tokens,
-        state: &State,
-    ) -> Result<Tokens, Report> {
-        let mut result = vec![];
-
-        fn highest_rec(tokens: Tokens) -> usize {
-            let mut highest: usize = 0;
-
-            for token in tokens {
-                if let Token::Group(inner) | Token::List(inner) = token {
-                    let inner_highest = highest_rec(inner);
-
-                    if inner_highest > highest {
-                        highest = inner_highest;
-                    }
-                } else if let Token::Parameter(index) = token {
-                    if index + 1 > highest {
-                        highest = index + 1
-                    }
-                }
-            }
+impl Range {
+    pub(crate) fn bounds(&self, len: usize) -> Option<std::ops::Range<usize>> {
+        let (start, end) = match self {
+            Range::Full(x) => (*x.start(), x.end().saturating_add(1)),
+            Range::To(x) => (x.start, len),
+            Range::From(x) => (0, x.end),
+        };
 
-            highest
+        if start <= end && end <= len {
+            Some(start..end)
+        } else {
+            None
         }
+    }
+}
 
+impl Utils for Tokens {
+    fn get_par(&mut self, func: &Token, state: &State, base: usize) -> Result<Tokens, Report> {
         let parameters = match func {
-            Token::Function(ident) => FUNCTIONS.get(&ident).unwrap().1.to_vec(),
+            Token::Function(ident) => ident.builtin().unwrap().1.to_vec(),
             Token::ActiveLambda(lambda) => {
                 vec!["Any"; highest_rec(lambda)]
             }
-            Token::Container(ident) => {
-                let container = state.get(&ident).unwrap();
+            Token::Container(ident) => match state.get(ident) {
+                Some(container) => parameter_types(container),
+                None => return Err(unknown_container(self, func)),
+            },
+            _ => unimplemented!(),
+        };
 
-                let all_tokens: Vec<Token> = container
-                    .iter()
-                    .fold(vec![], |acc, x| [acc, x.0.clone(), x.1.clone()].concat());
+        self.take_above(base, func, &parameters)
+    }
 
-                vec!["Any"; highest_rec(all_tokens)]
-            }
-            _ => unimplemented!(),
+    /// Takes parameters as [`Utils::take_par`] does from the values above
+    /// `base`, where those of the signed call being run start, since that
+    /// call can not reach the values of its caller beneath them.
+    fn take_above<T: AsRef<str>>(
+        &mut self,
+        base: usize,
+        func: &Token,
+        parameters: &[T],
+    ) -> Result<Tokens, Report> {
+        if base == 0 {
+            return self.take_par(func, parameters);
+        }
+
+        let mut above = self.split_off(base);
+        let result = above.take_par(func, parameters);
+
+        self.append(&mut above);
+        result
+    }
+
+    /// Takes the parameters of `func` off the top of the stack, top first,
+    /// leaving the stack as it was when they are missing or of the wrong type
+    /// so that it can be shown alongside `func` in the report.
+    fn take_par<T: AsRef<str>>(
+        &mut self,
+        func: &Token,
+        parameters: &[T],
+    ) -> Result<Tokens, Report> {
+        // Parameters ending in `...` take the rest of the stack along with
+        // them, for builtins that reach further down than they can name.
+        let (parameters, rest) = match parameters.split_last() {
+            Some((last, fixed)) if last.as_ref() == "..." => (fixed, true),
+            _ => (parameters, false),
         };
+
+        let valid = self.len() >= parameters.len()
+            && self
+                .iter()
+                .rev()
+                .zip(parameters)
+                .all(|(content, token_type)| has_type(content, token_type.as_ref()));
+
+        if valid {
+            let start = if rest {
+                0
+            } else {
+                self.len() - parameters.len()
+            };
+            return Ok(self.drain(start..).rev().collect());
+        }
+
+        let reference_code = [self.clone(), vec![func.clone()]].concat();
         let literal = reference_code.literal_enumerate();
         let mut final_report: Option<ReportBuilder<std::ops::Range<usize>, Source>> = None;
+        let mut provided = 0;
 
-        for (index, token_type) in parameters.clone().iter().enumerate() {
-            match self.pop() {
+        for (index, token_type) in parameters.iter().enumerate() {
+            match self.len().checked_sub(index + 1).map(|x| &self[x]) {
                 Some(content) => {
-                    if *token_type == "Any" || type_of(&content) == *token_type {
-                        result.push(content);
+                    if has_type(content, token_type.as_ref()) {
+                        provided += 1;
                         continue;
-                    } else if let Some(complex_type) = COMPLEX_TYPES.get(token_type) {
-                        if complex_type.contains(&type_of(&content).as_str()) {
-                            result.push(content);
-                            continue;
-                        }
                     }
 
-                    if let Some(report) = final_report {
-                        final_report = Some(
-                            report.with_label(
-                                Label::new(literal.1[literal.1.len() - index - 2].clone())
-                                    .with_message(format!(
-                                        "This has the type of {} but expected {}.",
-                                        type_of(&content),
-                                        *token_type
-                                    ))
-                                    .with_color(Color::Red),
-                            ),
-                        )
-                    } else {
-                        final_report = Some(
-                            Report::build(ReportKind::Error)
-                                .with_message("Mismatched Types")
-                                .with_label(
-                                    Label::new(literal.1[literal.1.len() - index - 2].clone())
-                                        .with_message(format!(
-                                            "This has the type of {} but expected {}.",
-                                            type_of(&content),
-                                            *token_type
-                                        ))
-                                        .with_color(Color::Red),
-                                ),
-                        )
-                    }
+                    let label = Label::new(literal.1[literal.1.len() - index - 2].clone())
+                        .with_message(format!(
+                            "This has the type of {} but expected {}.",
+                            type_of(content),
+                            token_type.as_ref()
+                        ))
+                        .with_color(Color::Red);
+
+                    final_report = Some(match final_report {
+                        Some(report) => report.with_label(label),
+                        None => Report::build(ReportKind::Error)
+                            .with_message("Mismatched Types")
+                            .with_label(label),
+                    })
                 }
                 None => {
                     let mut report = Report::build(ReportKind::Error)
@@ -165,35 +447,38 @@ impl Utils for Tokens {
                                 .with_message(format!(
                                     "This function expects the parameters ({}).",
                                     parameters
-                                        .clone()
                                         .iter()
-                                        .fold("".to_string(), |x, acc| format!("{} {}", acc, x))
+                                        .fold("".to_string(), |x, acc| format!(
+                                            "{} {}",
+                                            acc.as_ref(),
+                                            x
+                                        ))
                                         .trim()
                                 ))
                                 .with_color(Color::Red),
                         );
 
-                    if !result.is_empty() {
+                    if provided > 0 {
                         report = report.with_label(
                             Label::new(
                                 literal.1[0].clone().start
                                     ..literal.1[literal.1.len() - 2].clone().end,
                             )
-                            .with_message(format!("Only {} parameter(s) provided.", result.len()))
+                            .with_message(format!("Only {} parameter(s) provided.", provided))
                             .with_color(Color::Yellow),
                         )
                     }
 
-                    final_report = Some(report)
+                    final_report = Some(report);
+                    break;
                 }
             }
         }
 
-        if let Some(report) = final_report {
-            return Err(report.with_source(Source::from(literal.0)).finish());
-        }
-
-        Ok(result)
+        Err(final_report
+            .unwrap()
+            .with_source(Source::from(literal.0))
+            .finish())
     }
 
     fn as_nums(&self) -> Vec<f64> {
@@ -206,15 +491,24 @@ impl Utils for Tokens {
     }
 
     fn bundle(&self) -> Tokens {
-        static BUNDLES: &[(&str, &str, &str); 2] = &[("{", "}", "Group"), ("[", "]", "List")];
+        static BUNDLES: &[(&str, &str, &str); 5] = &[
+            ("{", "}", "Group"),
+            ("[", "]", "List"),
+            ("%{", "}", "Map"),
+            ("#{", "}", "Set"),
+            ("\\(", ")", "Lambda"),
+        ];
 
         let mut bundled = self.clone();
 
         for (first, second, collection) in BUNDLES {
-            if let Some((x, y)) = self.special_pairs(first, second) {
+            if let Some((x, y)) = bundled.special_pairs(first, second) {
                 let bundled_token = match *collection {
                     "Group" => Token::Group,
                     "List" => Token::List,
+                    "Map" => encode_map,
+                    "Set" => encode_set,
+                    "Lambda" => Token::Lambda,
                     _ => unimplemented!(),
                 }(bundled[x + 1..y].to_vec().bundle());
 
@@ -243,7 +537,7 @@ impl Utils for Tokens {
             if count == 0 {
                 second_index = Some(index + first_index?);
                 break;
-            } else if Token::Special(first.to_string()) == *value {
+            } else if matches!(value, Token::Special(x) if x.ends_with(&first[first.len() - 1..])) {
                 count += 1;
             } else if Token::Special(second.to_string()) == *value {
                 count -= 1;
@@ -292,7 +586,9 @@ impl Utils for Tokens {
 
             let mut token_range = 0..token_string.len();
 
-            if acc.0.is_empty() {
+            // Told apart by the ranges, as the first token may print as
+            // nothing, like an empty group does.
+            if acc.1.is_empty() {
                 acc = (token_string, vec![token_range]);
             } else {
                 token_range.start = acc.0.len() + 1;
@@ -307,7 +603,39 @@ impl Utils for Tokens {
 }
 
 pub fn encode_string(string: &str) -> Token {
-    Token::Group(string.chars().map(Token::Char).collect::<Vec<_>>())
+    Token::Str(string.to_string())
+}
+
+/// Builds a map from alternating keys and values, keeping the pairs sorted
+/// by key. Later values replace earlier ones with an equal key and a key
+/// without a value is given Null.
+pub fn encode_map(contents: Tokens) -> Token {
+    let mut pairs: Vec<(Token, Token)> = vec![];
+
+    for pair in contents.chunks(2) {
+        insert_pair(
+            &mut pairs,
+            pair[0].clone(),
+            pair.get(1).cloned().unwrap_or(Token::Null),
+        );
+    }
+
+    Token::Map(pairs)
+}
+
+/// Builds a set from the given tokens, sorted and without duplicates.
+pub fn encode_set(mut contents: Tokens) -> Token {
+    contents.sort_by(compare);
+    contents.dedup_by(|x, y| compare(x, y) == Ordering::Equal);
+
+    Token::Set(contents)
+}
+
+pub(crate) fn insert_pair(pairs: &mut Vec<(Token, Token)>, key: Token, value: Token) {
+    match pairs.binary_search_by(|(x, _)| compare(x, &key)) {
+        Ok(index) => pairs[index].1 = value,
+        Err(index) => pairs.insert(index, (key, value)),
+    }
 }
 
 pub trait Function {
@@ -316,15 +644,45 @@ pub trait Function {
 
 impl Function for State {
     fn resolve(&self, function: &Tokens, arr: &Tokens) -> Tokens {
-        let reversed: Tokens = arr.iter().cloned().rev().collect();
+        let substitute = |token: &Token| match token {
+            Token::Parameter(index) | Token::Capture(index) => arr[arr.len() - 1 - index].clone(),
+            _ => token.clone(),
+        };
+
+        // A `^n` at any depth inside a collection takes its value from here,
+        // whereas `$n` is left for the lambda the collection may become.
+        fn capture(tokens: &[Token], substitute: &impl Fn(&Token) -> Token) -> Tokens {
+            tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Capture(_) => substitute(token),
+                    Token::Group(inner) => Token::Group(capture(inner, substitute)),
+                    Token::List(inner) => Token::List(capture(inner, substitute)),
+                    Token::Lambda(inner) => Token::Lambda(capture(inner, substitute)),
+                    _ => token.clone(),
+                })
+                .collect()
+        }
+
         function
-            .par_iter()
-            .map(|token| {
-                if let Token::Parameter(index) = token {
-                    reversed[*index].clone()
-                } else {
-                    token.clone()
-                }
+            .iter()
+            .map(|token| match token {
+                Token::Record(name, fields) => Token::Record(
+                    name.to_string(),
+                    fields
+                        .iter()
+                        .map(|(field, value)| (field.to_string(), substitute(value)))
+                        .collect(),
+                ),
+                Token::Variant(name, tag, payload) => Token::Variant(
+                    name.to_string(),
+                    tag.to_string(),
+                    payload.iter().map(substitute).collect(),
+                ),
+                Token::Group(inner) => Token::Group(capture(inner, &substitute)),
+                Token::List(inner) => Token::List(capture(inner, &substitute)),
+                Token::Lambda(inner) => Token::Lambda(capture(inner, &substitute)),
+                _ => substitute(token),
             })
             .collect()
     }
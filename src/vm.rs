@@ -0,0 +1,603 @@
+use crate::evaluator::{check_results, define};
+use crate::utils::{captures, highest_rec, parameter_types, unknown_container, Function, Utils};
+use crate::{CodeBlock, FunctionRef, Signature, State, Symbol, Token, Tokens};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use charon_ariadne::Report;
+
+/// A single step of compiled code. Every token compiles to exactly one
+/// instruction, so the tokens left in a chunk can always be shown in reports.
+#[derive(Debug, Clone)]
+enum Instruction {
+    Push(Token),
+    Load(usize),
+    Substitute(Token),
+    Builtin(Token, FunctionRef, &'static [&'static str]),
+    Invoke(usize),
+    Group(Arc<Chunk>),
+    Execute(Token),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Chunk {
+    code: Vec<Instruction>,
+    tokens: Tokens,
+}
+
+/// A literal a guard can compare a parameter to without running any code,
+/// with values keyed so that 0 and -0 are the same.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Value(u64),
+    Atom(String),
+    Str(String),
+    Char(char),
+}
+
+impl Key {
+    fn of(token: &Token) -> Option<Key> {
+        match token {
+            Token::Value(x) if !x.is_nan() => Some(Key::Value((x + 0.0).to_bits())),
+            Token::Atom(x) => Some(Key::Atom(x.to_string())),
+            Token::Str(x) => Some(Key::Str(x.to_string())),
+            Token::Char(x) => Some(Key::Char(*x)),
+            _ => None,
+        }
+    }
+}
+
+/// Which case of a container applies, each guard leading to the index of its
+/// consequent. A run of guards comparing the same parameter to a literal, as
+/// in `$0 0 = -> ...`, becomes a single table to look that parameter up in.
+#[derive(Debug, Clone)]
+enum Guard {
+    Always(usize),
+    Test(Arc<Chunk>, usize),
+    Table {
+        parameter: usize,
+        cases: HashMap<Key, usize>,
+        tests: Vec<(Arc<Chunk>, usize)>,
+    },
+}
+
+impl Guard {
+    /// The parameter and literal compared by a guard such as `$0 0 =`.
+    fn comparison(predicate: &Tokens) -> Option<(usize, Key)> {
+        let (parameter, literal) = match predicate.as_slice() {
+            [Token::Parameter(x), literal, Token::Function(function)]
+            | [literal, Token::Parameter(x), Token::Function(function)]
+                if matches!(function.as_str(), "=" | "eq") =>
+            {
+                (*x, literal)
+            }
+            _ => return None,
+        };
+
+        Some((parameter, Key::of(literal)?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Compiled {
+    token: Token,
+    parameters: Vec<String>,
+    guards: Vec<Guard>,
+    consequents: Vec<Arc<Chunk>>,
+    signature: Option<Signature>,
+}
+
+impl Compiled {
+    fn name(&self) -> &'static str {
+        match self.token {
+            Token::Container(ident) => ident.as_str(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Every container in a state compiled to bytecode, with calls between them
+/// going straight to the container's index rather than through its name.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Program {
+    containers: Vec<Compiled>,
+    ids: HashMap<Symbol, usize>,
+}
+
+impl Program {
+    pub(crate) fn compile(state: &State) -> Program {
+        let ids = state
+            .keys()
+            .enumerate()
+            .map(|(index, name)| (*name, index))
+            .collect::<HashMap<_, _>>();
+
+        let mut program = Program {
+            containers: vec![],
+            ids,
+        };
+
+        let mut names = program.ids.iter().collect::<Vec<_>>();
+        names.sort_by_key(|(_, index)| **index);
+
+        let containers = names
+            .into_iter()
+            .map(|(name, _)| {
+                let container = &state[name];
+
+                Compiled {
+                    token: Token::Container(*name),
+                    parameters: parameter_types(container)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                    guards: program.guards(&container.cases),
+                    consequents: container
+                        .cases
+                        .iter()
+                        .map(|(_, consequent)| Arc::new(program.chunk(consequent, true)))
+                        .collect(),
+                    signature: container.signature.clone(),
+                }
+            })
+            .collect();
+
+        program.containers = containers;
+        program
+    }
+
+    fn guards(&self, cases: &[(Tokens, Tokens)]) -> Vec<Guard> {
+        let mut guards: Vec<Guard> = vec![];
+
+        for (index, (predicate, _)) in cases.iter().enumerate() {
+            if *predicate == vec![Token::Value(1.0)] {
+                guards.push(Guard::Always(index));
+                continue;
+            }
+
+            let test = Arc::new(self.chunk(predicate, true));
+
+            match (Guard::comparison(predicate), guards.last_mut()) {
+                (
+                    Some((x, key)),
+                    Some(Guard::Table {
+                        parameter,
+                        cases,
+                        tests,
+                    }),
+                ) if x == *parameter => {
+                    // An earlier guard for the same literal is tried first.
+                    cases.entry(key).or_insert(index);
+                    tests.push((test, index));
+                }
+                (Some((parameter, key)), _) => guards.push(Guard::Table {
+                    parameter,
+                    cases: HashMap::from([(key, index)]),
+                    tests: vec![(test, index)],
+                }),
+                (None, _) => guards.push(Guard::Test(test, index)),
+            }
+        }
+
+        guards
+    }
+
+    /// Compiles a sequence of tokens, where `body` says whether they belong
+    /// to a container, in which case its parameters are loaded from the frame
+    /// in the same places the tree evaluator would substitute them.
+    fn chunk(&self, tokens: &Tokens, body: bool) -> Chunk {
+        let code = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Parameter(index) | Token::Capture(index) if body => {
+                    Instruction::Load(*index)
+                }
+                Token::Record(..) | Token::Variant(..) if body => {
+                    Instruction::Substitute(token.clone())
+                }
+                Token::Group(..) | Token::List(..) | Token::Lambda(..)
+                    if body && captures(token) =>
+                {
+                    Instruction::Substitute(token.clone())
+                }
+                Token::Function(ident) => match ident.builtin() {
+                    Some((function, parameters)) => {
+                        Instruction::Builtin(token.clone(), *function, parameters)
+                    }
+                    None => Instruction::Execute(token.clone()),
+                },
+                Token::Container(ident) => match self.ids.get(ident) {
+                    Some(id) => Instruction::Invoke(*id),
+                    None => Instruction::Execute(token.clone()),
+                },
+                Token::Group(contents) => Instruction::Group(Arc::new(self.chunk(contents, false))),
+                Token::ActiveLambda(_) | Token::Void => Instruction::Execute(token.clone()),
+                _ => Instruction::Push(token.clone()),
+            })
+            .collect();
+
+        Chunk {
+            code,
+            tokens: tokens.clone(),
+        }
+    }
+}
+
+struct Frame {
+    chunk: Arc<Chunk>,
+    position: usize,
+    locals: Arc<Tokens>,
+}
+
+impl Frame {
+    fn finished(&self) -> bool {
+        self.position == self.chunk.code.len()
+    }
+}
+
+/// A call to a container with a signature whose consequent is running in
+/// the frame at `frame`, giving back the values above `base` once it is done.
+struct Call {
+    id: usize,
+    frame: usize,
+    base: usize,
+    tail_calls: Vec<usize>,
+}
+
+/// What a single run of bytecode is working on. Containers with a signature
+/// which were called last, with nothing beneath them, are kept in
+/// `tail_calls` of the call they are in, or of the thread when they are in
+/// none, to be checked against all that is left once it is over.
+#[derive(Default)]
+struct Thread {
+    stack: Tokens,
+    frames: Vec<Frame>,
+    calls: Vec<Call>,
+    tail_calls: Vec<usize>,
+}
+
+impl Thread {
+    /// Enters a frame, first dropping those with nothing left to run so that
+    /// a call made last replaces its caller rather than growing the frames.
+    /// The frame of a signed call is kept until its results are checked.
+    fn enter(&mut self, chunk: Arc<Chunk>, locals: Arc<Tokens>) {
+        while self.frames.last().is_some_and(Frame::finished)
+            && self.calls.last().map(|x| x.frame) != Some(self.frames.len() - 1)
+        {
+            self.frames.pop();
+        }
+
+        self.frames.push(Frame {
+            chunk,
+            position: 0,
+            locals,
+        })
+    }
+
+    /// Where the values of the innermost signed call start.
+    fn base(&self) -> usize {
+        self.calls.last().map_or(0, |x| x.base)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn take_par<T: AsRef<str>>(
+        &mut self,
+        func: &Token,
+        parameters: &[T],
+    ) -> Result<Tokens, Report> {
+        let base = self.base();
+        self.stack.take_above(base, func, parameters)
+    }
+}
+
+/// Runs bytecode against a state, which builtins are still given so that
+/// those evaluating code, such as map, can do so with the tree evaluator.
+struct Machine<'a> {
+    state: &'a mut State,
+    program: &'a Program,
+}
+
+#[allow(clippy::result_large_err)]
+impl Machine<'_> {
+    fn run(&mut self, chunk: Arc<Chunk>, locals: Arc<Tokens>) -> Result<Tokens, Report> {
+        let mut thread = Thread::default();
+
+        thread.enter(chunk, locals);
+
+        while let Some(frame) = thread.frames.last_mut() {
+            let chunk = Arc::clone(&frame.chunk);
+
+            let instruction = match chunk.code.get(frame.position) {
+                Some(instruction) => instruction,
+                None => {
+                    thread.frames.pop();
+
+                    if let Some(call) = thread.calls.pop_if(|x| x.frame == thread.frames.len()) {
+                        self.finish(call, &thread)?
+                    }
+
+                    continue;
+                }
+            };
+
+            frame.position += 1;
+
+            match instruction {
+                Instruction::Push(token) => thread.stack.push(token.clone()),
+                Instruction::Load(index) => {
+                    let token = frame.locals[*index].clone();
+                    self.execute(token, &mut thread)?
+                }
+                Instruction::Substitute(token) => {
+                    let parameters = reversed(&frame.locals);
+
+                    for token in self.state.resolve(&vec![token.clone()], &parameters) {
+                        self.execute(token, &mut thread)?
+                    }
+                }
+                Instruction::Builtin(token, function, parameters) => {
+                    self.builtin(token, *function, parameters, &mut thread)?
+                }
+                Instruction::Invoke(id) => self.invoke(*id, &mut thread)?,
+                Instruction::Group(contents) => {
+                    let contents = self.run(Arc::clone(contents), Arc::new(vec![]))?;
+                    thread.stack.push(Token::Group(contents))
+                }
+                Instruction::Execute(token) => self.execute(token.clone(), &mut thread)?,
+            }
+        }
+
+        for id in thread.tail_calls.iter().rev() {
+            let container = &self.program.containers[*id];
+
+            check_results(
+                container.name(),
+                container.signature.as_ref().unwrap(),
+                &thread.stack,
+                || vec![container.token.clone()],
+                0,
+            )?;
+        }
+
+        Ok(thread.stack)
+    }
+
+    /// Runs a token that was not known when compiling, such as the result of
+    /// a builtin, just as the tree evaluator would when it comes to it next.
+    fn execute(&mut self, token: Token, thread: &mut Thread) -> Result<(), Report> {
+        match token {
+            Token::Function(ref ident) => {
+                let (function, parameters) = ident.builtin().unwrap();
+                self.builtin(&token, *function, parameters, thread)
+            }
+            Token::Container(ref ident) => match self.program.ids.get(ident) {
+                Some(id) => self.invoke(*id, thread),
+                None => Err(unknown_container(&thread.stack, &token)),
+            },
+            Token::ActiveLambda(ref lambda) => {
+                let parameters = thread.take_par(&token, &vec!["Any"; highest_rec(lambda)])?;
+                let resolved = self.state.resolve(lambda, &parameters);
+
+                thread.enter(
+                    Arc::new(self.program.chunk(&resolved, false)),
+                    Arc::new(vec![]),
+                );
+
+                Ok(())
+            }
+            Token::Group(contents) => {
+                let contents = self.run(
+                    Arc::new(self.program.chunk(&contents, false)),
+                    Arc::new(vec![]),
+                )?;
+                thread.stack.push(Token::Group(contents));
+                Ok(())
+            }
+            Token::Void => Ok(()),
+            _ => {
+                thread.stack.push(token);
+                Ok(())
+            }
+        }
+    }
+
+    fn builtin(
+        &mut self,
+        token: &Token,
+        function: FunctionRef,
+        parameters: &[&str],
+        thread: &mut Thread,
+    ) -> Result<(), Report> {
+        let parameters = thread.take_par(token, parameters)?;
+
+        match function {
+            FunctionRef::Single(function) => {
+                let result = function(self.state, parameters)?;
+                self.execute(result, thread)
+            }
+            FunctionRef::Many(function) => {
+                thread.stack.extend(function(self.state, parameters)?);
+                Ok(())
+            }
+        }
+    }
+
+    fn invoke(&mut self, id: usize, thread: &mut Thread) -> Result<(), Report> {
+        let program = self.program;
+        let container = &program.containers[id];
+        let parameters = thread.take_par(&container.token, &container.parameters)?;
+        let locals = Arc::new(reversed(&parameters));
+
+        let mut selected = None;
+
+        for guard in &container.guards {
+            selected = match guard {
+                Guard::Always(case) => Some(*case),
+                Guard::Test(predicate, case) => self.test(predicate, &locals, *case)?,
+                Guard::Table {
+                    parameter,
+                    cases,
+                    tests,
+                } => match &locals[*parameter] {
+                    // Tokens which would be run when loaded are left to the
+                    // guards themselves, rather than compared as they are.
+                    Token::Function(_)
+                    | Token::Container(_)
+                    | Token::ActiveLambda(_)
+                    | Token::Group(_)
+                    | Token::Void => {
+                        let mut selected = None;
+
+                        for (predicate, case) in tests {
+                            selected = self.test(predicate, &locals, *case)?;
+
+                            if selected.is_some() {
+                                break;
+                            }
+                        }
+
+                        selected
+                    }
+                    token => Key::of(token).and_then(|key| cases.get(&key).copied()),
+                },
+            };
+
+            if selected.is_some() {
+                break;
+            }
+        }
+
+        let consequent = Arc::clone(&container.consequents[selected.unwrap()]);
+
+        if container.signature.is_none() {
+            thread.enter(consequent, locals);
+            return Ok(());
+        }
+
+        let frame = thread.calls.last().map_or(0, |x| x.frame);
+        let tail = thread.stack.len() == thread.base()
+            && thread.frames[frame..].iter().all(Frame::finished);
+
+        if tail {
+            let tail_calls = match thread.calls.last_mut() {
+                Some(call) => &mut call.tail_calls,
+                None => &mut thread.tail_calls,
+            };
+
+            if !tail_calls.contains(&id) {
+                tail_calls.push(id);
+            }
+
+            thread.enter(consequent, locals);
+            return Ok(());
+        }
+
+        // The consequent runs on this thread in a frame of its own, rather
+        // than in a run of its own, so that recursion through it does not
+        // grow the native stack. Its results are checked as the frame ends.
+        thread.enter(consequent, locals);
+        thread.calls.push(Call {
+            id,
+            frame: thread.frames.len() - 1,
+            base: thread.stack.len(),
+            tail_calls: vec![],
+        });
+
+        Ok(())
+    }
+
+    /// Runs a guard, giving back its case when it holds.
+    fn test(
+        &mut self,
+        predicate: &Arc<Chunk>,
+        locals: &Arc<Tokens>,
+        case: usize,
+    ) -> Result<Option<usize>, Report> {
+        let result = self.run(Arc::clone(predicate), Arc::clone(locals))?;
+
+        Ok((result == vec![Token::Value(1.0)]).then_some(case))
+    }
+
+    /// Checks what a signed call gave back, once the frame it ran in is over.
+    fn finish(&self, call: Call, thread: &Thread) -> Result<(), Report> {
+        let container = &self.program.containers[call.id];
+        let results = &thread.stack[call.base..];
+
+        for id in call.tail_calls.iter().rev() {
+            let container = &self.program.containers[*id];
+
+            check_results(
+                container.name(),
+                container.signature.as_ref().unwrap(),
+                results,
+                || vec![container.token.clone()],
+                0,
+            )?;
+        }
+
+        check_results(
+            container.name(),
+            container.signature.as_ref().unwrap(),
+            results,
+            || {
+                let mut reference = [
+                    thread.stack[..call.base].to_vec(),
+                    vec![container.token.clone()],
+                ]
+                .concat();
+
+                for frame in thread.frames.iter().rev() {
+                    reference.append(&mut remaining(frame));
+                }
+
+                reference
+            },
+            call.base,
+        )
+    }
+}
+
+fn reversed(tokens: &Tokens) -> Tokens {
+    tokens.iter().cloned().rev().collect()
+}
+
+/// The tokens a frame has yet to run, with its parameters filled in.
+fn remaining(frame: &Frame) -> Tokens {
+    frame.chunk.tokens[frame.position..]
+        .iter()
+        .map(|token| match token {
+            Token::Parameter(index) | Token::Capture(index) if *index < frame.locals.len() => {
+                frame.locals[*index].clone()
+            }
+            _ => token.clone(),
+        })
+        .collect()
+}
+
+/// Runs parsed code with the bytecode backend, defining containers just as
+/// the tree evaluator does and compiling the state again whenever it changes.
+#[allow(clippy::result_large_err)]
+pub(crate) fn codeblock_run(
+    state: &mut State,
+    data: Vec<CodeBlock>,
+) -> Result<Vec<Tokens>, Report> {
+    let mut result: Vec<Tokens> = Vec::new();
+    let mut program: Option<Program> = None;
+
+    for codeblock in data {
+        match codeblock.container {
+            Some(_) => {
+                define(state, codeblock);
+                program = None;
+            }
+            None => {
+                let program = program.get_or_insert_with(|| Program::compile(state));
+                let chunk = Arc::new(program.chunk(&codeblock.default_case, false));
+
+                result.push(Machine { state, program }.run(chunk, Arc::new(vec![]))?);
+            }
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,749 @@
+use charon_core::evaluator::{Backend, Evaluate};
+use charon_core::utils::Utils;
+use charon_core::State;
+
+const BACKENDS: [Backend; 2] = [Backend::Tree, Backend::Bytecode];
+
+fn run(code: &str, backend: Backend) -> Result<Vec<String>, usize> {
+    match State::new().apply_with(code, backend) {
+        Ok(blocks) => Ok(blocks.iter().map(|block| block.literal()).collect()),
+        Err(reports) => Err(reports.len()),
+    }
+}
+
+fn assert_gives(code: &str, expected: &[&str]) {
+    for backend in BACKENDS {
+        assert_eq!(
+            run(code, backend),
+            Ok(expected.iter().map(|x| x.to_string()).collect()),
+            "{:?}",
+            backend
+        );
+    }
+}
+
+fn assert_fails(code: &str) {
+    for backend in BACKENDS {
+        assert!(run(code, backend).is_err(), "{:?}", backend);
+    }
+}
+
+#[test]
+fn arithmetic() {
+    assert_gives("1 2 add 4 mul\n10 4 sub\n7 2 mod\n", &["12", "6", "1"]);
+}
+
+#[test]
+fn recursion() {
+    assert_gives(
+        "
+factorial <-|
+  $0 2 < -> 1
+  $0 $0 1 sub factorial mul
+
+fib <-|
+  $0 2 < -> $0
+  $0 1 - fib $0 2 - fib +
+
+5 factorial
+10 fib
+",
+        &["120", "55"],
+    );
+}
+
+#[test]
+fn guards() {
+    assert_gives(
+        "
+sign <-|
+  $0 0 < -> :negative
+  $0 0 = -> :zero
+  :positive
+
+-3 sign
+0 sign
+2 sign
+",
+        &[":negative", ":zero", ":positive"],
+    );
+}
+
+#[test]
+fn literal_guards() {
+    // Runs of guards comparing a parameter to literals are looked up at once
+    // by the bytecode backend, which has to agree with trying them in order.
+    assert_gives(
+        "
+name <-|
+  $0 0 = -> :zero
+  :two $0 eq -> :two
+  $0 0 = -> :again
+  $0 \"three\" = -> :three
+  $0 'c' = -> :char
+  $0 10 > -> :large
+  $1 0 = -> :second
+  :other
+
+-0 1 name
+:two 1 name
+\"three\" 1 name
+{'t' 'h' 'r' 'e' 'e'} 1 name
+'c' 1 name
+20 1 name
+-5 0 name
+5 1 name
+",
+        &[
+            ":zero", ":two", ":three", ":three", ":char", ":large", ":second", ":other",
+        ],
+    );
+    assert_fails("[missing] lambda call\n");
+}
+
+#[test]
+fn splicing() {
+    // An unsigned container runs in its caller, so it can take values from
+    // below its own parameters.
+    assert_gives("plus <- add\n\n1 2 plus\n1 2 plus 4 plus\n", &["3", "7"]);
+}
+
+#[test]
+fn sorting() {
+    assert_gives(
+        "
+[3 1 2] sort
+[3 1 2] \\( $0 $1 - ) sort_by
+[3 1 2] \\( $1 $0 - ) sort_by
+[[2 :a] [1 :b] [2 :c] [1 :d]] \\( $0 0 nth $1 0 nth - ) sort_by
+[[2 :a] [1 :b] [2 :c] [1 :d]] \\( $0 0 nth ) sort_by_key
+[\"bb\" \"a\" \"ccc\"] \\( $0 len ) sort_by_key
+",
+        &[
+            "[1 2 3]",
+            "[1 2 3]",
+            "[3 2 1]",
+            "[[1 :b] [1 :d] [2 :a] [2 :c]]",
+            "[[1 :b] [1 :d] [2 :a] [2 :c]]",
+            "[a bb ccc]",
+        ],
+    );
+    assert_fails("[3 1 2] \\( :a ) sort_by\n");
+    assert_fails("[3 1 2] \\( 0 ) sort_by\n");
+    assert_fails("[3 1 2] \\( $0 $1 ) sort_by\n");
+    assert_fails("[3 1 2] \\( $0 axe ) sort_by_key\n");
+}
+
+#[test]
+fn slices() {
+    assert_gives(
+        "[1 2 3] #..18446744073709551615 slice\n[1 2 3] #1..18446744073709551615 slice\n",
+        &["Null", "Null"],
+    );
+    // Ranges include both ends, and a string slices to a string.
+    assert_gives(
+        "[1 2 3] #1..2 slice\n[1 2 3] #1.. slice\n[1 2 3] #..1 slice\n",
+        &["[2 3]", "[2 3]", "[1 2]"],
+    );
+    assert_gives(
+        "{ 1 2 3 } #1..2 slice\n{ 1 2 3 } #1.. slice\n{ 1 2 3 } #..1 slice\n",
+        &["{2 3}", "{2 3}", "{1 2}"],
+    );
+    assert_gives(
+        "\"abc\" #1..2 slice\n\"abc\" #1.. slice\n\"abc\" #..1 slice\n",
+        &["bc", "bc", "ab"],
+    );
+    assert_gives(
+        "[1 2 3] 1 nth\n[1 2 3] 3 nth\n\"abc\" 5 nth\n",
+        &["2", "Null", "Null"],
+    );
+    assert_gives(
+        "[1 2 3] 1 9 set_nth\n[1 2 3] 3 9 insert_at\n[1 2 3] 0 remove_at\n\"abc\" 1 'x' set_nth\n",
+        &["[1 9 3]", "[1 2 3 9]", "[2 3]", "axc"],
+    );
+    assert_fails("[1 2 3] 3 9 set_nth\n");
+    assert_fails("[1 2 3] 4 9 insert_at\n");
+    assert_fails("[1 2 3] 3 remove_at\n");
+    assert_fails("\"abc\" 3 remove_at\n");
+}
+
+#[test]
+fn comparison() {
+    assert_gives(
+        "
+0 -1 mul 0 eq
+0 0 div 0 0 div eq
+0 0 div 0 0 div neq
+1 2 less
+1 2 greater
+1 2 <
+1 2 >
+2 2 >=
+3 2 <=
+\"abc\" \"abd\" <
+'a' 'b' compare
+[1 2 3] [1 2] compare
+[1 [2 :x]] [1 [2 :x]] eq
+{1 2} {1 3} neq
+:a \"a\" eq
+",
+        &[
+            "1", "0", "1", "0", "1", "1", "0", "1", "0", "1", "-1", "1", "1", "1", "0",
+        ],
+    );
+}
+
+#[test]
+fn maps() {
+    assert_gives(
+        "
+%{:a 1 \"b\" 2 3 {1 2}} \"b\" get
+%{:a 1} :z get
+%{:a 1} :b 2 put
+%{:a 1 :b 2} :a remove
+%{:b 2 :a 1} keys
+%{:b 2 :a 1} values
+%{:a 1} :a has_key
+%{:a 1 :b 2} %{:b 3 :c 4} merge
+%{:a 1 :b 2} iter collect_list
+[[:x 1] [:y 2]] iter collect_map
+",
+        &[
+            "2",
+            "Null",
+            "%{:a 1 :b 2}",
+            "%{:b 2}",
+            "[:a :b]",
+            "[1 2]",
+            "1",
+            "%{:a 1 :b 3 :c 4}",
+            "[[:a 1] [:b 2]]",
+            "%{:x 1 :y 2}",
+        ],
+    );
+    assert_fails("%{:a 1 :b}\n");
+    assert_fails("%{[1] 2}\n");
+    assert_fails("%{:a %{{1} 2}}\n");
+    assert_fails("[[[1] 2]] iter collect_map\n");
+}
+
+#[test]
+fn groups_and_lists() {
+    assert_gives(
+        "
+double <- $0 2 *
+
+{1 double 2 double}
+[1 2 3] iter [ $0 2 mul ] lambda map collect_list
+[3 1 2] sort
+",
+        &["{2 4}", "[2 4 6]", "[1 2 3]"],
+    );
+}
+
+#[test]
+fn parallel() {
+    assert_gives(
+        "
+square <- $0 $0 *
+
+noisy <- $0 OUTPUT $0
+
+[1 2 3 4 5] iter `square` par_map collect_list
+[1 2 3 4 5 6] iter [ $0 2 mod 0 eq ] lambda par_filter collect_list
+[1 2 3] iter `noisy` par_map collect_list
+",
+        &["[1 4 9 16 25]", "[2 4 6]", "[1 2 3]"],
+    );
+
+    // Enough elements for map to spread them across threads when asked to,
+    // which has to give the same as par_map and as map on a single thread.
+    let elements = (0..1000).map(|x| x.to_string()).collect::<Vec<_>>();
+    let code = |function: &str| {
+        format!(
+            "square <- $0 $0 *\n\n[{}] iter `square` {} collect_list\n",
+            elements.join(" "),
+            function
+        )
+    };
+    let expected = format!(
+        "[{}]",
+        (0..1000)
+            .map(|x| (x * x).to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+
+    for backend in BACKENDS {
+        let mut state = State::new();
+        state.set_parallel(true);
+
+        for result in [
+            run(&code("map"), backend),
+            run(&code("par_map"), backend),
+            state
+                .apply_with(&code("map"), backend)
+                .map(|blocks| blocks.iter().map(|x| x.literal()).collect())
+                .map_err(|x| x.len()),
+        ] {
+            assert_eq!(result, Ok(vec![expected.clone()]), "{:?}", backend);
+        }
+    }
+}
+
+#[test]
+fn closures() {
+    assert_gives(
+        "
+scale <- [ $0 ^0 * ] lambda map
+
+adder k <- [ $0 k + ] lambda
+
+offset k <- k 2 * let double [ $0 double * k + ] lambda map
+
+shift : Iterator Value -> Iterator <- $0 [ $0 ^1 - ] lambda map
+
+curried <- \\( \\( $0 ^0 + ) )
+
+nested <- [ [ ^0 ] { ^0 ^0 } ]
+
+pair <- [ $0 $1 ]
+
+gather a b <- a 1 + let x [ $0 $1 x b + + + ] lambda
+
+[1 2 3] iter 10 scale collect_list
+5 3 adder call
+[1 2 3] iter 10 offset collect_list
+[4 5] iter 3 shift collect_list
+1 7 curried call call
+1 2 10 20 gather call
+3 nested
+1 2 3 pair
+",
+        &[
+            "[10 20 30]",
+            "8",
+            "[30 50 70]",
+            "[1 2]",
+            "8",
+            "34",
+            "[[3] {3 3}]",
+            "1 [$0 $1]",
+        ],
+    );
+    assert_fails("f : Value -> Value <- [ ^1 ] lambda call\n\n1 f\n");
+}
+
+#[test]
+fn lambda_literals() {
+    assert_gives(
+        "
+adder k <- \\( $0 k + )
+
+5 \\( $0 1 + ) call
+[1 2 3] iter \\( $0 $0 * ) map collect_list
+[1 2 3] iter \\( $0 $1 + ) 0 foldr
+5 3 adder call
+4 \\( $0 \\( $0 2 * ) call ) call
+\\( $0 [ $0 ] )
+",
+        &["6", "[1 4 9]", "6", "8", "8", "\\($0 [$0])"],
+    );
+}
+
+#[test]
+fn combinators() {
+    assert_gives(
+        "
+add_three a b c <- a b c + +
+
+5 `add` curry
+[1 2 3] iter 10 `add` curry map collect_list
+10 1 2 `add_three` 2 partial call
+4 \\( $0 1 + ) \\( $0 2 * ) compose call
+10 3 `sub` flip call
+[1 2] iter `identity` map collect_list
+[1 2 3] iter 0 const map collect_list
+",
+        &[
+            "\\(5 add)",
+            "[11 12 13]",
+            "13",
+            "10",
+            "-7",
+            "[1 2]",
+            "[0 0 0]",
+        ],
+    );
+    assert_fails("1 `add` 1.5 partial\n");
+}
+
+#[test]
+fn stack_words() {
+    assert_gives(
+        "
+square <- dup *
+
+total : Value -> Value <- $0 1 2 depth + + +
+
+1 2 swap
+1 2 drop 3 nip
+1 2 over
+1 2 3 rot
+1 2 3 -rot
+1 2 tuck
+1 2 2dup
+1 2 3 2 pick
+1 2 3 2 roll
+1 2 3 depth
+1 2 3 clear
+4 square
+10 total
+",
+        &[
+            "2 1", "3", "1 2 1", "2 3 1", "3 1 2", "2 1 2", "1 2 1 2", "1 2 3 1", "2 3 1",
+            "1 2 3 3", "", "16", "16",
+        ],
+    );
+    assert_gives("1 2add\n", &["3"]);
+    assert_fails("1 2 5 pick\n");
+    assert_fails("pick\n");
+}
+
+#[test]
+fn named_parameters() {
+    assert_gives(
+        "
+hyp a b <- a a * b b * +
+
+norm x y <- x x * let xx y y * let yy xx yy + let total total
+
+shadow a <- a 1 + let x x 2 * let x x 1 +
+
+grp a b <- { a b }
+
+deep a b <- [ [ a ] [ [ b ] ] ]
+
+add_to a <- \\( $0 a + )
+
+twice a <- a 2 * let x { [ x ] \\( $0 x * ) }
+
+3 4 hyp
+3 4 norm
+3 shadow
+1 2 grp
+1 2 deep
+5 add_to 3 swap call
+3 twice
+",
+        &[
+            "25",
+            "25",
+            "9",
+            "{1 2}",
+            "[[1] [[2]]]",
+            "8",
+            "{[6] \\($0 6 *)}",
+        ],
+    );
+    assert_fails("bad a <- { a b }\n\n1 bad\n");
+}
+
+#[test]
+fn signatures() {
+    assert_gives(
+        "
+inc : Value -> Value <- $0 1 [$0 $1 +] lambda call
+
+count : Value -> Value <-|
+  $0 0 = -> 0
+  $0 1 - count
+
+5 inc
+3 count
+",
+        &["6", "0"],
+    );
+}
+
+#[test]
+fn records_and_unions() {
+    assert_gives(
+        "
+Point <-: x y
+
+Option <-: :some value | :none
+
+unwrap_or <-|
+  (:some x) (_) -> x
+  (:none) (d) -> d
+  $1
+
+3 4 Point Point_y
+5 some 0 unwrap_or
+none 7 unwrap_or
+",
+        &["4", "5", "7"],
+    );
+}
+
+#[test]
+fn patterns() {
+    assert_gives(
+        "
+sum_list <-|
+  ([]) -> 0
+  ([h ...t]) -> h t sum_list +
+  0
+
+describe <-|
+  (0) -> \"zero\"
+  ({a b}) -> \"pair\"
+  (n@Value) -> n 1 +
+  \"other\"
+
+Option <-: :some value | :none
+
+unwrap <-|
+  (:some x) -> [x x]
+  ({a b}) -> { b a } 1 \\( $0 a b + * ) call
+  :none
+
+[1 2 3 4] sum_list
+0 describe
+{1 2} describe
+41 describe
+:x describe
+5 some unwrap
+{1 2} unwrap
+",
+        &["10", "zero", "pair", "42", "other", "[5 5]", "{2 1} 3"],
+    );
+}
+
+#[test]
+fn errors() {
+    assert_fails("5 add\n");
+    assert_fails("\"a\" 1 add\n");
+    assert_fails("f : Value -> Value <- $0 \"x\"\n\n3 f\n");
+    assert_fails("f : Value -> Value <- $0 $0\n\n3 f\n");
+}
+
+#[test]
+fn exhaustiveness() {
+    let union = "Option <-: :some value | :none\n\n";
+    let count = |code: &str| State::new().warnings(&format!("{}{}", union, code)).len();
+
+    assert_eq!(count("f <-|\n  (:some x) -> x\n  (:none) -> 0\n"), 0);
+    assert_eq!(count("f <-|\n  (:some x) -> x\n"), 1);
+    assert_eq!(count("f <-|\n  (:some 0) -> 0\n  (:none) -> 0\n"), 1);
+    assert_eq!(count("f <-|\n  (:some x) -> x\n  0\n"), 0);
+    assert_eq!(count("f <-|\n  (:some x) (0) -> x\n  (_) (_) -> 0\n"), 0);
+    assert_eq!(count("f <-|\n  ([]) -> 0\n  ([h ...t]) -> h\n"), 0);
+    assert_eq!(
+        count("f <-|\n  ([]) -> 0\n  ([a]) -> a\n  ([a b ...t]) -> a\n"),
+        0
+    );
+    assert_eq!(count("f <-|\n  ([]) -> 0\n  ([a b ...t]) -> a\n"), 1);
+    assert_eq!(count("f <-|\n  ({}) -> 0\n  ([h ...t]) -> h\n"), 1);
+
+    // Patterns that match only some values warn without a default case,
+    // whether or not they match on a union.
+    assert_eq!(count("f <-|\n  (0) -> 1\n"), 1);
+    assert_eq!(count("f <-|\n  (0) -> 1\n  (x) -> x\n"), 0);
+    assert_eq!(count("f <-|\n  (0) -> 1\n  2\n"), 0);
+    assert_eq!(count("f <-|\n  $0 0 = -> 1\n"), 0);
+
+    // Unions defined by earlier code are known to the state.
+    let mut state = State::new();
+    assert!(state.apply(union).is_ok());
+    assert_eq!(state.warnings("f <-|\n  (:none) -> 0\n").len(), 1);
+}
+
+#[test]
+fn strings() {
+    assert_gives(
+        "\"ab\" \"cd\" concat\n\"a,b,c\" \",\" split\n[ \"x\" \"y\" ] \", \" join\n\"  hi  \" trim\n",
+        &["abcd", "[a b c]", "x, y", "hi"],
+    );
+    assert_gives(
+        "\"Hi\" upper\n\"Hi\" lower\n\"a-b-c\" \"-\" \"+\" replace\n",
+        &["HI", "hi", "a+b+c"],
+    );
+    assert_gives(
+        "\"hello\" \"ell\" contains\n\"hello\" \"he\" starts_with\n\"hello\" \"he\" ends_with\n",
+        &["1", "1", "0"],
+    );
+    assert_gives(
+        "\"hello\" \"l\" find\n\"hello\" \"z\" find\n\"12.5\" to_number\n\"x\" to_number\n",
+        &["2", "Null", "12.5", "Null"],
+    );
+    assert_gives(
+        "[ 1 2 ] to_string len\n\"x = {}, y = {}\" [ 1 \"b\" ] format\n\"hi\" type\n",
+        &["5", "x = 1, y = b", "String"],
+    );
+
+    // Signatures can ask for a string.
+    assert_gives(
+        "shout : String -> String <-\n  $0 upper\n\n\"hey\" shout\n",
+        &["HEY"],
+    );
+    assert_fails("shout : String -> String <-\n  $0 upper\n\n1 shout\n");
+    assert_fails("\"ab\" \"b\" +\n");
+
+    // An empty group is still a Group, so it is not taken for a string.
+    assert_gives(
+        "{} type\n\"\" type\n{ 'a' } type\n",
+        &["Group", "String", "String"],
+    );
+    assert_gives("f <-|\n  ({}) -> 1\n  0\n\n{} f\n{ 1 } f\n", &["1", "0"]);
+    assert_fails("shout : String -> String <-\n  $0 upper\n\n{} shout\n");
+    assert_fails("{} 1 +\n");
+}
+
+#[test]
+fn string_tokens() {
+    assert_gives(
+        "\"ab\" \"ab\" =\n\"ab\" \"abc\" =\n\"abc\" len\n\"abc\" 1 nth\n",
+        &["1", "0", "3", "Char('b')"],
+    );
+    assert_gives(
+        "\"abc\" chars collect_string\n\"abc\" reverse\n\"abc\" chars collect_list len\n",
+        &["abc", "cba", "3"],
+    );
+
+    // Builtins taking any Iterable take strings apart by character.
+    assert_gives(
+        "\"abc\" \", \" join\n\"{}-{}\" \"ab\" format\n\"abc\" tail\n",
+        &["a, b, c", "a-b", "bc"],
+    );
+    assert_fails("\"abc\" sum\n");
+    assert_fails("\"\" sum\n");
+}
+
+#[test]
+fn sets() {
+    assert_gives(
+        "#{ 3 1 2 1 }\n#{ :b :a \"c\" 1 }\n#{ 1 2 } #{ 2 1 } =\n#{ 1 2 } type\n",
+        &["#{1 2 3}", "#{1 c :a :b}", "1", "Set"],
+    );
+    assert_gives(
+        "#{ 1 2 } #{ 2 3 } union\n#{ 1 2 } #{ 2 3 } intersection\n#{ 1 2 } #{ 2 3 } difference\n",
+        &["#{1 2 3}", "#{2}", "#{1}"],
+    );
+    assert_gives(
+        "#{ 1 2 } 3 insert\n#{ 1 2 } 2 remove\n#{ 1 2 } 2 contains\n#{ 1 2 } 5 contains\n",
+        &["#{1 2 3}", "#{1}", "1", "0"],
+    );
+    assert_gives(
+        "[ 2 2 1 ] to_set\n[ 2 2 1 ] iter collect_set len\n",
+        &["#{1 2}", "2"],
+    );
+    // Set and map membership agrees with equality, which takes -0 for 0.
+    assert_gives(
+        "#{ 0 -0 } len\n#{ 0 } -0 contains\n%{0 :a} -0 get\n%{0 :a} -0 has_key\n%{0 :a -0 :b} len\n",
+        &["1", "1", ":a", "1", "1"],
+    );
+    // NaN equals nothing, but a set holds only one of it.
+    assert_gives(
+        "0 0 / 0 0 / =\n#{ 0 } 0 0 / insert 0 0 / insert len\n",
+        &["0", "2"],
+    );
+}
+
+#[test]
+fn records() {
+    let point = "Point <-: x y\n\nSize <-: w\n\n";
+    let with_point = |code: &str| format!("{}{}", point, code);
+
+    assert_gives(
+        &with_point("3 4 Point type\n3 4 Point :x field\n3 4 Point Point_y\n"),
+        &["Point", "3", "4"],
+    );
+    assert_fails(&with_point("3 4 Point :z field\n"));
+
+    // Record names can be used as parameter types, and Record takes any.
+    let norm = "norm : Point -> Value <- $0 Point_x $0 Point_y +\n\nfirst : Record -> Any <- $0 :w field\n\n";
+    assert_gives(
+        &with_point(&format!("{}3 4 Point norm\n5 Size first\n", norm)),
+        &["7", "5"],
+    );
+    assert_fails(&with_point(&format!("{}1 Size norm\n", norm)));
+    assert_fails(&with_point(&format!("{}1 first\n", norm)));
+}
+
+#[test]
+fn arity() {
+    // A signature fixes how many values are taken, even ones the body
+    // never mentions, where otherwise the highest parameter decides.
+    assert_gives(
+        "first : Value Value -> Value <- $0\n\nfirst_of <- $0\n\n1 2 3 first\n1 2 3 first_of\n",
+        &["1 2", "1 2 3"],
+    );
+    assert_gives(
+        "second : Value Value -> Value <- $1\n\ndist a b : Value Value -> Value <- a b -\n\n1 2 second\n5 2 dist\n",
+        &["2", "3"],
+    );
+    assert_fails("f : Value -> Value <- $0\n\n:a f\n");
+    assert_fails("f : Value -> Value Value <- $0\n\n1 f\n");
+    assert_fails("dist a : Value Value -> Value <- a\n\n5 2 dist\n");
+}
+
+#[test]
+fn stack_effects() {
+    // Annotations are only read by the checker and do not change how code
+    // runs, even when they are wrong.
+    assert_gives(
+        "sq <- ( a -- b ) $0 $0 *\n\nbad <- ( a -- b c ) $0 $0 *\n\ndist a b <- ( a b -- c ) a b -\n\n3 sq\n3 bad\n5 2 dist\n",
+        &["9", "9", "3"],
+    );
+    assert_gives("f <-| ( a -- b )\n  $0 0 = -> 1\n  $0\n\n0 f\n", &["1"]);
+    assert_fails("sq <- $0 ( a -- b ) $0 *\n\n3 sq\n");
+    assert_fails("( a -- b ) 1\n");
+}
+
+#[test]
+fn in_place_evaluation() {
+    // Consequents are spliced where the call was, between the values below
+    // it and the code after it.
+    assert_gives(
+        "two <- $0 $0\n\ninc <- $0 1 +\n\ntwice <- $0 inc inc\n\n1 two 2 +\n1 twice 10 twice +\n",
+        &["1 3", "15"],
+    );
+
+    let long = format!("0{}\n", " 1 +".repeat(5000));
+    assert_gives(&long, &["5000"]);
+
+    assert_fails("f <- $0 $1 +\n\n1 f\n");
+    assert_fails(&format!("0{} +\n", " 1 +".repeat(100)));
+}
+
+#[test]
+fn guards_against_the_state() {
+    // Predicates can call containers, and an error in one stops the call.
+    assert_gives(
+        "is_zero <- $0 0 =\n\nf <-|\n  $0 is_zero -> :zero\n  :other\n\n0 f\n5 f\n",
+        &[":zero", ":other"],
+    );
+    assert_fails("f <-|\n  $0 \"a\" + -> 1\n  2\n\n0 f\n");
+
+    // Guarded recursion works the same with many other containers defined.
+    let name = |x: u8| {
+        format!(
+            "unused_{}{}",
+            (b'a' + x / 26) as char,
+            (b'a' + x % 26) as char
+        )
+    };
+    let many = (0..250)
+        .map(|x| format!("{} <- $0 {} +\n\n", name(x), x))
+        .collect::<String>();
+    assert_gives(
+        &format!(
+            "{}count <-|\n  $0 0 = -> 0\n  $0 1 - count\n\n3000 count\n10 {}\n",
+            many,
+            name(249)
+        ),
+        &["0", "259"],
+    );
+}
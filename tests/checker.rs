@@ -0,0 +1,158 @@
+use charon_core::checker::{check, effects};
+use charon_core::Effect;
+
+/// Each report as it would be printed, without colours.
+fn rendered(code: &str) -> Vec<String> {
+    check(code)
+        .iter()
+        .map(|report| {
+            let mut bytes = vec![];
+            report.write(&mut bytes).unwrap();
+
+            let text = String::from_utf8(bytes).unwrap();
+            let mut plain = String::new();
+            let mut escaped = false;
+
+            for character in text.chars() {
+                match character {
+                    '\u{1b}' => escaped = true,
+                    'm' if escaped => escaped = false,
+                    _ if escaped => {}
+                    _ => plain.push(character),
+                }
+            }
+
+            plain
+        })
+        .collect()
+}
+
+/// The title of each report along with the source text its labels underline.
+fn reported(code: &str) -> Vec<(String, Vec<String>)> {
+    rendered(code)
+        .iter()
+        .map(|text| {
+            let lines = text
+                .lines()
+                .map(|x| x.chars().collect())
+                .collect::<Vec<Vec<_>>>();
+            let title = text.lines().next().unwrap_or_default();
+            let marked = lines
+                .windows(2)
+                .filter(|pair| {
+                    let marks = pair[1].iter().skip_while(|x| **x != '·').skip(1);
+
+                    marks.clone().all(|x| " ─┬╮".contains(*x)) && marks.clone().any(|x| *x != ' ')
+                })
+                .map(|pair| {
+                    pair[0]
+                        .iter()
+                        .zip(&pair[1])
+                        .skip_while(|(_, x)| **x != '·')
+                        .skip(1)
+                        .filter(|(_, x)| **x != ' ')
+                        .map(|(x, _)| *x)
+                        .collect()
+                })
+                .collect();
+
+            (title.split_once(": ").unwrap().1.to_string(), marked)
+        })
+        .collect()
+}
+
+fn assert_reports(code: &str, expected: &[(&str, &[&str])]) {
+    assert_eq!(
+        reported(code),
+        expected
+            .iter()
+            .map(|(title, marked)| (
+                title.to_string(),
+                marked.iter().map(|x| x.to_string()).collect()
+            ))
+            .collect::<Vec<_>>()
+    );
+}
+
+fn effect(inputs: &[&str], outputs: &[&str]) -> Effect {
+    Effect {
+        inputs: inputs.iter().map(|x| x.to_string()).collect(),
+        outputs: outputs.iter().map(|x| x.to_string()).collect(),
+    }
+}
+
+#[test]
+fn well_typed() {
+    assert_reports(
+        "
+factorial <-|
+  $0 1 < -> 1
+  $0 1 - factorial $0 *
+
+5 factorial
+",
+        &[],
+    );
+}
+
+#[test]
+fn stack_underflow() {
+    assert_reports("1 2 +\n\n1   +\n", &[("Stack Underflow", &["+"])]);
+}
+
+#[test]
+fn mismatched_types() {
+    // Labels point at the code as it was written, spacing and all.
+    assert_reports(
+        "inc <- $0 1 +\n\n\"a\"   inc\n",
+        &[("Mismatched Types", &["\"a\""])],
+    );
+    assert_reports("{ 1   \"x\" + } 2\n", &[("Mismatched Types", &["\"x\""])]);
+    assert!(rendered("1\n\n{ 1   \"x\" + } 2\n")[0].contains("{ 1   \"x\" + } 2"));
+    assert_reports(
+        "f <-|\n  $0 0 = -> \"zero\"   1 +\n  $0\n",
+        &[("Mismatched Types", &["\"zero\""])],
+    );
+}
+
+#[test]
+fn signature_results() {
+    assert_reports(
+        "h : Value -> String <-\n  $0   1 +\n",
+        &[("Mismatched Types", &["$0   1 +"])],
+    );
+    assert_reports(
+        "h : Value -> Value Value <-\n  $0 1 +\n",
+        &[("Mismatched Results", &["$0 1 +"])],
+    );
+    assert_reports("h : Value -> Value <-\n  $0 1 +\n", &[]);
+}
+
+#[test]
+fn stack_effects() {
+    assert_reports(
+        "g <- ( a -- a b ) $0\n",
+        &[("Mismatched Stack Effect", &["( a -- a b )"])],
+    );
+    assert_reports("g <- ( a -- a ) $0\n", &[]);
+    assert_reports(
+        "g <-|\n  $0 0 = -> 1 2\n  3\n",
+        &[("Inconsistent Stack Effect", &["3"])],
+    );
+}
+
+#[test]
+fn container_effects() {
+    let found = effects("sq <- $0 $0 *\n\nswap_sub <- $1 $0 -\n")
+        .ok()
+        .unwrap();
+
+    assert_eq!(found["sq"], effect(&["Value"], &["Value"]));
+    assert_eq!(found["swap_sub"], effect(&["Value", "Value"], &["Value"]));
+
+    // An annotation is trusted for code whose effect can not be known.
+    let found = effects("run <- ( a -- b ) $0 call\n").ok().unwrap();
+    assert_eq!(found["run"], effect(&["Any"], &["Any"]));
+
+    assert!(effects("1 +++ \"\n").is_err());
+}
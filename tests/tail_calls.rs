@@ -0,0 +1,115 @@
+use charon_core::evaluator::{Backend, Evaluate};
+use charon_core::{State, Token};
+
+// Counting down a million times by recursion only fits in a test thread's
+// stack when each call in tail position takes over from its caller.
+const COUNTDOWN: &str = "
+countdown <-|
+  $0 0 = -> :done
+  $0 1 - countdown
+
+1000000 countdown
+";
+
+const TYPED_COUNTDOWN: &str = "
+countdown : Value -> Atom <-|
+  $0 0 = -> :done
+  $0 1 - countdown
+
+1000000 countdown
+";
+
+// Adding up after each call returns keeps every call of a typed container
+// pending, which has to fit without the evaluators recursing natively.
+const TYPED_TOTAL: &str = "
+tot : Value -> Value <-|
+  $0 0 = -> 0
+  $0 1 - tot $0 +
+
+100000 tot
+";
+
+fn assert_total(backend: Backend) {
+    match State::new().apply_with(TYPED_TOTAL, backend) {
+        Ok(result) => assert_eq!(result, vec![vec![Token::Value(5000050000.0)]]),
+        Err(_) => panic!("{:?} could not add up", backend),
+    }
+}
+
+fn assert_done(code: &str, backend: Backend) {
+    match State::new().apply_with(code, backend) {
+        Ok(result) => assert_eq!(result, vec![vec![Token::Atom("done".to_string())]]),
+        Err(_) => panic!("{:?} could not count down", backend),
+    }
+}
+
+#[test]
+fn countdown() {
+    assert_done(COUNTDOWN, Backend::Tree);
+}
+
+#[test]
+fn countdown_bytecode() {
+    assert_done(COUNTDOWN, Backend::Bytecode);
+}
+
+#[test]
+fn typed_countdown() {
+    assert_done(TYPED_COUNTDOWN, Backend::Tree);
+}
+
+#[test]
+fn typed_countdown_bytecode() {
+    assert_done(TYPED_COUNTDOWN, Backend::Bytecode);
+}
+
+// What a typed container gives back from a tail call is still checked.
+#[test]
+fn typed_tail_call_results() {
+    let code = "
+countdown : Value -> Value <-|
+  $0 0 = -> :done
+  $0 1 - countdown
+
+3 countdown
+";
+
+    for backend in [Backend::Tree, Backend::Bytecode] {
+        assert!(
+            State::new().apply_with(code, backend).is_err(),
+            "{:?}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn typed_total() {
+    assert_total(Backend::Tree);
+}
+
+#[test]
+fn typed_total_bytecode() {
+    assert_total(Backend::Bytecode);
+}
+
+// A typed container only sees the values it takes, not those of its caller,
+// and what it gives back from a call beneath others is still checked.
+#[test]
+fn typed_call_boundaries() {
+    let codes = [
+        "f : Value -> Value <- $0 +\n\n1 2 f\n",
+        "f : Value -> Value <-|\n  $0 0 = -> :done\n  $0 1 - f\n\n1 3 f +\n",
+    ];
+
+    for code in codes {
+        for backend in [Backend::Tree, Backend::Bytecode] {
+            assert!(
+                State::new().apply_with(code, backend).is_err(),
+                "{:?} {}",
+                backend,
+                code
+            );
+        }
+    }
+}